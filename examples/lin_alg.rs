@@ -20,7 +20,7 @@ fn main() -> Result<(), MainError> {
     };
     for i in -10..=10 {
         let point = vec3a(i as f32 / 10.0, 1.0, 1.0);
-        println!("{point}->{}",raster.project_point(point));
+        println!("{point}->{}", raster.project_point(point));
     }
     Ok(())
 }
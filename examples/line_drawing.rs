@@ -1,10 +1,7 @@
 #![allow(unused_imports)]
-use error_stack::{
-    Report,
-    ResultExt,
-    Result
-};
+use error_stack::{Report, Result, ResultExt};
 use text_3d_graphics::prelude::*;
+use text_3d_graphics::printing::lines::LineStyle;
 use thiserror::Error;
 
 struct RotatingLine {
@@ -21,34 +18,34 @@ impl Behaviour for RotatingLine {
     ) -> std::result::Result<ProcessNext, Box<dyn std::error::Error>> {
         self.theta += self.ang_vel * delta;
 
-        let dims = buffer.dimensions().clone();
+        let dims = *buffer.dimensions();
         let center = vec2(dims.x as f32 / 2.0, dims.y as f32 / 2.0);
         let terminal = Vec2::from_angle(self.theta) * self.len + center;
 
         buffer.fill(' ', RgbColor(255, 255, 255));
         buffer.draw_line(
-            ivec2(dims.x as i32 - 1, dims.y as i32 - 1), 
-            ivec2(0, dims.y as i32 - 1), 
-            |_, _| (Some('.'), Some(RgbColor(255, 0, 0)))
+            ivec2(dims.x as i32 - 1, dims.y as i32 - 1),
+            ivec2(0, dims.y as i32 - 1),
+            |_, _| (Some('.'), Some(RgbColor(255, 0, 0))),
         )?;
+        buffer.draw_line(ivec2(0, dims.y as i32 - 1), ivec2(0, 0), |_, _| {
+            (Some('.'), Some(RgbColor(255, 0, 0)))
+        })?;
         buffer.draw_line(
-            ivec2(0, dims.y as i32 - 1), 
-            ivec2(0, 0), 
-            |_, _| (Some('.'), Some(RgbColor(255, 0, 0)))
+            ivec2(dims.x as i32 - 1, 0),
+            ivec2(dims.x as i32 - 1, dims.y as i32 - 1),
+            |_, _| (Some('.'), Some(RgbColor(255, 0, 0))),
         )?;
-        buffer.draw_line(
-            ivec2(dims.x as i32 - 1, 0), 
-            ivec2(dims.x as i32 - 1, dims.y as i32 - 1), 
-            |_, _| (Some('.'), Some(RgbColor(255, 0, 0)))
-        )?;
-        buffer.draw_line(
-            ivec2(0, 0), 
-            ivec2(dims.x as i32 - 1, 0), 
-            |_, _| (Some('.'), Some(RgbColor(255, 0, 0)))
-        )?;
-        buffer.draw_line(
-            ivec2(center.x as i32, center.y as i32),
-            ivec2(terminal.x as i32, terminal.y as i32),
+        buffer.draw_line(ivec2(0, 0), ivec2(dims.x as i32 - 1, 0), |_, _| {
+            (Some('.'), Some(RgbColor(255, 0, 0)))
+        })?;
+        buffer.draw_line_styled(
+            center,
+            terminal,
+            &LineStyle::builder()
+                .thickness(2.0)
+                .anti_aliased(true)
+                .build(),
             |_, _| (Some('.'), Some(RgbColor(255, 255, 255))),
         )?;
 
@@ -62,13 +59,13 @@ struct MainError;
 
 fn main() -> Result<(), MainError> {
     let mut runner = Runner::builder()
-        .behaviour(RotatingLine{
+        .behaviour(RotatingLine {
             theta: 0.0,
             len: 45.0,
             ang_vel: 1.5,
         })
         .dimensions(uvec2(100, 100))
         .build();
-    runner.run();
+    runner.run().unwrap();
     Ok(())
 }
@@ -1,4 +1,4 @@
-use error_stack::{Report, Result, ResultExt};
+use error_stack::{Result, ResultExt};
 use text_3d_graphics::prelude::*;
 use thiserror::Error;
 
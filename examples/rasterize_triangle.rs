@@ -24,7 +24,7 @@ impl Behaviour for DrawTriangle {
         }
 
         // self.triangle.v = self.triangle.v.map(|v| v + vec3a(0.0,0.0,0.3 * delta));
-        
+
         let rot = -15.0f32.to_radians() * delta;
         self.rasterizer.camera.rotate_y_radians(rot);
 
@@ -52,6 +52,7 @@ fn main() -> Result<(), MainError> {
             vec3a(1.0, 1.0, 2.0),
         ],
         color: RgbColor(255, 100, 200),
+        ..Default::default()
     };
     let triangle2 = Triangle {
         v: [
@@ -60,6 +61,7 @@ fn main() -> Result<(), MainError> {
             vec3a(1.0, 1.0, -2.0),
         ],
         color: RgbColor(111, 222, 200),
+        ..Default::default()
     };
     let mut my_runner = Runner::builder()
         .fps(10.0)
@@ -0,0 +1,104 @@
+#![allow(unused_imports)]
+use error_stack::{Report, Result, ResultExt};
+use text_3d_graphics::prelude::*;
+use thiserror::Error;
+
+struct Orbit {
+    pub rasterizer: Rasterizer,
+    pub scene: Scene,
+    pub pivot: NodeId,
+    pub moon_pivot: NodeId,
+}
+
+impl Behaviour for Orbit {
+    fn process(
+        &mut self,
+        buffer: &mut CharBuffer,
+        delta: f32,
+    ) -> std::result::Result<ProcessNext, Box<dyn std::error::Error>> {
+        buffer.fill('.', RgbColor(000, 000, 000));
+        self.rasterizer.clear_frame();
+
+        // Rotating a pivot moves everything below it in the hierarchy
+        let pivot = self.scene.node_mut(self.pivot).unwrap();
+        pivot.transform.rotation *= Quat::from_rotation_y(0.8 * delta);
+        let moon_pivot = self.scene.node_mut(self.moon_pivot).unwrap();
+        moon_pivot.transform.rotation *= Quat::from_rotation_y(2.5 * delta);
+
+        self.rasterizer.render_scene(&self.scene, buffer)?;
+
+        Ok(ProcessNext::Continue)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("there was a error encountered in the main fn")]
+struct MainError;
+
+fn main() -> Result<(), MainError> {
+    let mut scene = Scene::new();
+    let sun = Node::builder()
+        .name("sun")
+        .mesh(
+            SimpleMesh::icosphere_builder()
+                .color(RgbColor(255, 200, 50))
+                .build(),
+        )
+        .build();
+    scene
+        .add_node(sun, None)
+        .change_context_lazy(|| MainError)?;
+
+    let pivot = scene
+        .add_node(Node::builder().name("pivot").build(), None)
+        .change_context_lazy(|| MainError)?;
+    let planet = Node::builder()
+        .name("planet")
+        .transform(Transform::from_translation(vec3(2.5, 0.0, 0.0)))
+        .mesh(
+            SimpleMesh::uv_sphere_builder()
+                .radius(0.5)
+                .color(RgbColor(50, 120, 255))
+                .build(),
+        )
+        .build();
+    let planet = scene
+        .add_node(planet, Some(pivot))
+        .change_context_lazy(|| MainError)?;
+    let moon_pivot = scene
+        .add_node(Node::builder().name("moon pivot").build(), Some(planet))
+        .change_context_lazy(|| MainError)?;
+    let moon = Node::builder()
+        .name("moon")
+        .transform(Transform::from_translation(vec3(0.9, 0.0, 0.0)))
+        .mesh(SimpleMesh::cube_builder().size(0.3).build())
+        .build();
+    scene
+        .add_node(moon, Some(moon_pivot))
+        .change_context_lazy(|| MainError)?;
+
+    let mut rasterizer = Rasterizer {
+        camera: Camera::builder()
+            .position(vec3(0.0, 0.0, -6.0))
+            .fov_y_radians(90.0f32.to_radians())
+            .aspect_ratio(1.0)
+            .build(),
+        tile_size: Some(uvec2(16, 16)),
+        ..Rasterizer::default()
+    };
+    rasterizer.detect_cell_aspect();
+    let mut my_runner = Runner::builder()
+        .fps(10.0)
+        .dimensions(uvec2(50, 50))
+        .color(RgbColor(000, 000, 000))
+        .character('.')
+        .behaviour(Orbit {
+            rasterizer,
+            scene,
+            pivot,
+            moon_pivot,
+        })
+        .build();
+    my_runner.run().unwrap();
+    Ok(())
+}
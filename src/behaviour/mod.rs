@@ -48,6 +48,7 @@ impl<B: Behaviour> Runner<B> {
 
 impl<B: Behaviour> Runner<B> {
     #[inline]
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         format!("{}", self.buffer)
     }
@@ -61,11 +62,11 @@ impl<B: Behaviour> Runner<B> {
         execute!(
             std::io::stdout(),
             crossterm::terminal::Clear(crossterm::terminal::ClearType::Purge),
-            cursor::MoveTo(0,0),
+            cursor::MoveTo(0, 0),
             cursor::Hide,
             cursor::SavePosition,
         )?;
-        for i in 0.. {
+        for _i in 0.. {
             let delta = if let Some(inst) = self.last_frame {
                 let elapsed = inst.elapsed().as_secs_f32();
                 let d = self.spf - elapsed;
@@ -4,26 +4,36 @@ pub mod behaviour;
 pub mod printing;
 pub mod rasterizing;
 pub mod resources;
+pub mod scene;
 
 pub mod prelude {
     pub use {
         super::{
             behaviour::{Behaviour, ProcessNext, Runner},
-            printing::CharBuffer,
-            resources::{SimpleMesh, Triangle},
-            rasterizing::{Camera,Rasterizer},
+            printing::{Cell, CharBuffer},
+            rasterizing::{
+                draw::{CullMode, FrontFace, Instance, PolygonMode},
+                fog::{Fog, FogFalloff},
+                lighting::{Attenuation, Light, LightKind, ShadingMode},
+                Camera, Projection, Rasterizer,
+            },
+            resources::{simplify::LodMesh, SimpleMesh, Triangle},
+            scene::{Node, NodeId, Scene, Transform},
         },
-        anstyle::{Color, RgbColor, Style},
+        anstyle::{Color, Effects, RgbColor, Style},
         glam::{
-            f32::{mat4, quat, vec2, vec3, vec3a, vec4, Affine3A, Mat4, Quat, Vec2, Vec3, Vec3A, Vec4},
-            i32::{ivec2, IVec2},
+            f32::{
+                mat4, quat, vec2, vec3, vec3a, vec4, Affine3A, Mat4, Quat, Vec2, Vec3, Vec3A, Vec4,
+            },
+            i32::{ivec2, ivec3, IVec2, IVec3},
             u32::{uvec2, UVec2},
         },
     };
 }
 
 pub(crate) use {
-    anstyle::{Color, RgbColor, Style},
+    anstyle::{Color, Effects, RgbColor, Style},
+    buildstructor::buildstructor,
     crossterm::{
         cursor, execute,
         style::Print,
@@ -33,12 +43,11 @@ pub(crate) use {
     getset::{CopyGetters, Getters, MutGetters, Setters},
     glam::{
         f32::{mat4, quat, vec2, vec3, vec3a, vec4, Affine3A, Mat4, Quat, Vec2, Vec3, Vec3A, Vec4},
-        i32::{ivec2, IVec2},
+        i32::{ivec2, ivec3, IVec2, IVec3},
         u32::{uvec2, UVec2},
     },
     itertools::Itertools,
-    std::sync::Arc,
     std::rc::Rc,
+    std::sync::Arc,
     thiserror::Error,
-    buildstructor::buildstructor,
 };
@@ -1,4 +1,16 @@
 pub(crate) use super::*;
+use glam::I64Vec2;
+
+/// The character and color a shading callback wants written to a cell, `None` leaves that part of
+/// the cell untouched.
+pub type Shade = (Option<char>, Option<RgbColor>);
+
+/// Fractional bits of the fixed point coordinates triangles are rasterized with.
+pub const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: i64 = 1 << SUBPIXEL_BITS;
+/// Vertices are clamped this many cells away from the origin, so that edge functions of far off
+/// screen vertices can't overflow.
+const MAX_COORDINATE: f32 = (1 << 22) as f32;
 
 #[derive(Debug, Error)]
 pub enum DrawError {
@@ -9,111 +21,79 @@ pub enum DrawError {
 }
 
 impl CharBuffer {
-    pub fn draw_triangle(
-        &mut self,
-        mut verticies: [IVec2; 3],
-        mut shading: impl FnMut(IVec2, &CharBuffer) -> (Option<char>, Option<RgbColor>),
-    ) -> Result<(), DrawError> {
-        verticies.sort_by(|a, b| a.y.cmp(&b.y));
-
-        let top_triangle = verticies[2].y != verticies[1].y;
-        let bottom_triangle = verticies[0].y != verticies[1].y;
-
-        if !top_triangle && !bottom_triangle {
-            return Ok(());
-        }
-
-        let delta = verticies[2] - verticies[0];
-
-        // The point on the edge of the triangle opposite to the middle vertex with the same value
-        // as the middle vertex. A line between this vertex and the middle vertex would be
-        // horizontal and cut the triangle in half.
-        let midpoint = ivec2(
-            verticies[0].x.saturating_add( delta.x.saturating_mul(verticies[1].y - verticies[0].y) / delta.y),
-            verticies[1].y,
-        );
-
-        if top_triangle {
-            self.draw_top_triangle([midpoint, verticies[1], verticies[2]], &mut shading)?;
+    /// Writes whichever parts of `shade` are set to the cell at `position`.
+    ///
+    /// # Safety
+    /// `position` must be within the buffer's dimensions.
+    #[inline]
+    pub(crate) unsafe fn apply_shade_unchecked(&mut self, position: UVec2, shade: Shade) {
+        let cell = self.get_unchecked_mut(position);
+        if let Some(char) = shade.0 {
+            cell.char = char;
         }
-        if bottom_triangle {
-            self.draw_bottom_triangle([verticies[0], verticies[1], midpoint], &mut shading)?;
+        if let Some(color) = shade.1 {
+            cell.fg = color;
         }
-
-        Ok(())
     }
-    fn draw_top_triangle(
+    pub fn draw_triangle(
         &mut self,
-        mut verticies: [IVec2; 3],
-        mut shading: &mut impl FnMut(IVec2, &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        verticies: [IVec2; 3],
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
-        println!("top called!: {verticies:?}");
-        let delta = (
-            verticies[2] - verticies[1],
-            verticies[2] - verticies[0],
+        //! Fills the cells whose centers lie inside the triangle between the centers of the
+        //! `verticies` cells. Cells on an edge shared by two triangles are only filled by one of
+        //! them, see [`CharBuffer::draw_triangle_subpixel`].
+        self.draw_triangle_fixed(
+            verticies.map(|v| v.as_i64vec2() * SUBPIXEL_SCALE + SUBPIXEL_SCALE / 2),
+            shading,
         );
-        let row_edges = |y: i32| -> (i32, i32) {
-            let a = verticies[1].x.saturating_add( delta.0.x.saturating_mul(y - verticies[1].y) / delta.0.y);
-            let b = verticies[0].x.saturating_add( delta.1.x.saturating_mul(y - verticies[0].y) / delta.1.y);
-
-            (std::cmp::min(a, b), std::cmp::max(a, b))
-        };
-        for ver in (verticies[0].y..=verticies[2].y)
-            .map(|y| {
-                let edges = row_edges(y);
-                (edges.0..=edges.1).map(move |x| ivec2(x, y))
-            })
-            .flatten()
-        {
-            if self.is_valid_point(ver) {
-                let shade = shading(ver, &self);
-                self.set_char(uvec2(ver.x as u32, ver.y as u32), shade.0, shade.1)
-                    .change_context_lazy(|| DrawError::Triangle(verticies))
-                    .attach_printable_lazy(|| format!("Failed at point: {ver}"))?;
-            }
-        }
         Ok(())
     }
-    fn draw_bottom_triangle(
+    pub fn draw_triangle_subpixel(
         &mut self,
-        mut verticies: [IVec2; 3],
-        shading: &mut impl FnMut(IVec2, &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        verticies: [Vec2; 3],
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
-        println!("bottom called!: {verticies:?}");
-        let delta = (
-            verticies[0] - verticies[1],
-            verticies[0] - verticies[2],
-        );
-        let row_edges = |y: i32| -> (i32, i32) {
-            let a = verticies[1].x.saturating_add( delta.0.x.saturating_mul(y - verticies[1].y) / delta.0.y);
-            let b = verticies[2].x.saturating_add( delta.1.x.saturating_mul(y - verticies[2].y) / delta.1.y);
-
-            (std::cmp::min(a, b), std::cmp::max(a, b))
-        };
-        for ver in (verticies[0].y..=verticies[2].y)
-            .map(|y| {
-                let edges = row_edges(y);
-                (edges.0..=edges.1).map(move |x| ivec2(x, y))
-            })
-            .flatten()
-        {
-            if self.is_valid_point(ver) {
-                let shade = shading(ver, &self);
-                self.set_char(uvec2(ver.x as u32, ver.y as u32), shade.0, shade.1)
-                    .change_context_lazy(|| DrawError::Triangle(verticies))
-                    .attach_printable_lazy(|| format!("Failed at point: {ver}"))?;
-            }
-        }
+        //! Fills the cells whose centers lie inside a triangle given in continuous cell
+        //! coordinates, where cell `(x, y)` spans `x..x + 1` and `y..y + 1`. Vertices are snapped
+        //! to 1/256th of a cell. Centers exactly on an edge are only filled if it is a top or
+        //! left edge, so triangles sharing an edge cover each cell along it exactly once.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let mut cb = CharBuffer::new(uvec2(8, 8), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! let quad = [vec2(0.0, 0.0), vec2(8.0, 0.0), vec2(8.0, 8.0), vec2(0.0, 8.0)];
+        //! for triangle in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+        //!     cb.draw_triangle_subpixel(triangle, |_, _| (Some('.'), None)).unwrap();
+        //! }
+        //! assert!(cb.cells().iter().all(|c| c.char == '.'));
+        //! ```
+        self.draw_triangle_fixed(verticies.map(to_fixed), shading);
         Ok(())
     }
+    pub(crate) fn draw_triangle_fixed(
+        &mut self,
+        verticies: [I64Vec2; 3],
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) {
+        //! Half space rasterization of a triangle in fixed point cell coordinates with
+        //! [`SUBPIXEL_BITS`] fractional bits.
+        let dimensions = self.dimensions;
+        for_each_covered_cell(dimensions, verticies, |p| {
+            let shade = shading(p, self);
+            // SAFETY: only cells within the dimensions are covered
+            unsafe { self.apply_shade_unchecked(p.as_uvec2(), shade) };
+        });
+    }
     pub fn draw_line(
         &mut self,
         mut start_point: IVec2,
         mut end_point: IVec2,
-        shading: fn(pos: IVec2, buf: &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
+        //! Draws a one cell wide line between the two cells, see
+        //! [`CharBuffer::draw_line_styled`] for anti-aliased, thick and patterned lines.
         if start_point.x == end_point.x {
-            return self.draw_vertical_line(start_point, end_point, shading);
+            return self.draw_vertical_line(start_point, end_point, &mut shading);
         } else if start_point.x > end_point.x {
             std::mem::swap(&mut start_point, &mut end_point);
         }
@@ -122,11 +102,11 @@ impl CharBuffer {
         let slope = dif_vec.y as f32 / dif_vec.x as f32;
 
         if slope.abs() > 1.0 {
-            self.draw_steep_line(start_point, end_point, slope, shading)?;
+            self.draw_steep_line(start_point, end_point, slope, &mut shading)?;
             return Ok(());
         }
 
-        self.draw_shallow_line(start_point, end_point, slope, shading)?;
+        self.draw_shallow_line(start_point, end_point, slope, &mut shading)?;
 
         Ok(())
     }
@@ -134,11 +114,11 @@ impl CharBuffer {
         &mut self,
         mut start_point: IVec2,
         mut end_point: IVec2,
-        shading: fn(pos: IVec2, buf: &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        shading: &mut impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
         if start_point.y == end_point.y {
             if self.is_valid_point(start_point) {
-                let (character, color) = shading(ivec2(start_point.x, start_point.y), &self);
+                let (character, color) = shading(ivec2(start_point.x, start_point.y), self);
                 return self
                     .set_char(
                         uvec2(start_point.x as u32, start_point.y as u32),
@@ -156,73 +136,111 @@ impl CharBuffer {
         let x = start_point.x;
 
         (std::cmp::max(0, start_point.y)..=std::cmp::min(end_point.y, self.dimensions.y as i32 - 1))
-            .map(|y| {
-                let (character, color) = shading(ivec2(x, y), &self);
+            .try_for_each(|y| {
+                let (character, color) = shading(ivec2(x, y), self);
                 self.set_char(uvec2(x as u32, y as u32), character, color)
                     .change_context_lazy(|| DrawError::Line(start_point, end_point))
                     .attach_printable_lazy(|| format!("Failed to print char at {x},{y}"))
             })
-            .fold(Ok(()), |accum, r| {
-                if r.is_err() && !accum.is_err() {
-                    r
-                } else {
-                    accum
-                }
-            })
     }
     fn draw_steep_line(
         &mut self,
         mut start_point: IVec2,
         mut end_point: IVec2,
         slope: f32,
-        shading: fn(pos: IVec2, buf: &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        shading: &mut impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
         if start_point.y > end_point.y {
             std::mem::swap(&mut start_point, &mut end_point)
         }
-        (start_point.y..=end_point.y)
-            .map(|y| {
-                let x = ((y - start_point.y) as f32 * 1.0 / slope + start_point.x as f32) as i32;
-                if !self.is_valid_point(ivec2(x, y)) {
-                    return Ok(());
-                }
-                let (character, color) = shading(ivec2(x, y), &self);
-                self.set_char(uvec2(x as u32, y as u32), character, color)
-                    .change_context_lazy(|| DrawError::Line(start_point, end_point))
-                    .attach_printable_lazy(|| format!("failed at point {x},{y}"))
-            })
-            .fold(Ok(()), |accum, r| {
-                if r.is_err() && accum.is_ok() {
-                    r
-                } else {
-                    accum
-                }
-            })
+        (start_point.y..=end_point.y).try_for_each(|y| {
+            let x = ((y - start_point.y) as f32 * 1.0 / slope + start_point.x as f32) as i32;
+            if !self.is_valid_point(ivec2(x, y)) {
+                return Ok(());
+            }
+            let (character, color) = shading(ivec2(x, y), self);
+            self.set_char(uvec2(x as u32, y as u32), character, color)
+                .change_context_lazy(|| DrawError::Line(start_point, end_point))
+                .attach_printable_lazy(|| format!("failed at point {x},{y}"))
+        })
     }
     fn draw_shallow_line(
         &mut self,
         start_point: IVec2,
         end_point: IVec2,
         slope: f32,
-        shading: fn(pos: IVec2, buf: &CharBuffer) -> (Option<char>, Option<RgbColor>),
+        shading: &mut impl FnMut(IVec2, &CharBuffer) -> Shade,
     ) -> Result<(), DrawError> {
-        (start_point.x..=end_point.x)
-            .map(|x| {
-                let y = ((x - start_point.x) as f32 * slope + start_point.y as f32) as i32;
-                if !self.is_valid_point(ivec2(x, y)) {
-                    return Ok(());
-                }
-                let (character, color) = shading(ivec2(x, y), &self);
-                self.set_char(uvec2(x as u32, y as u32), character, color)
-                    .change_context_lazy(|| DrawError::Line(start_point, end_point))
-                    .attach_printable_lazy(|| format!("failed at point {x},{y}"))
-            })
-            .fold(Ok(()), |accum, r| {
-                if r.is_err() && accum.is_ok() {
-                    r
-                } else {
-                    accum
-                }
-            })
+        (start_point.x..=end_point.x).try_for_each(|x| {
+            let y = ((x - start_point.x) as f32 * slope + start_point.y as f32) as i32;
+            if !self.is_valid_point(ivec2(x, y)) {
+                return Ok(());
+            }
+            let (character, color) = shading(ivec2(x, y), self);
+            self.set_char(uvec2(x as u32, y as u32), character, color)
+                .change_context_lazy(|| DrawError::Line(start_point, end_point))
+                .attach_printable_lazy(|| format!("failed at point {x},{y}"))
+        })
+    }
+}
+
+/// Snaps a point in cell coordinates to the fixed point grid triangles are rasterized on.
+pub(crate) fn to_fixed(v: Vec2) -> I64Vec2 {
+    let v = v.clamp(Vec2::splat(-MAX_COORDINATE), Vec2::splat(MAX_COORDINATE));
+    (v * SUBPIXEL_SCALE as f32).round().as_i64vec2()
+}
+
+pub(crate) fn for_each_covered_cell(
+    dimensions: UVec2,
+    mut verticies: [I64Vec2; 3],
+    mut visit: impl FnMut(IVec2),
+) {
+    //! Visits every cell of a `dimensions` sized grid whose center a triangle in fixed point
+    //! cell coordinates covers, following the top-left rule.
+    let edge = |a: I64Vec2, b: I64Vec2, p: I64Vec2| (b - a).perp_dot(p - a);
+    let area = edge(verticies[0], verticies[1], verticies[2]);
+    if area == 0 {
+        return;
+    }
+    if area < 0 {
+        verticies.swap(1, 2);
+    }
+
+    // Bounding box of the cells whose centers could be covered, clipped to the grid
+    let half = SUBPIXEL_SCALE / 2;
+    let lo = verticies[0].min(verticies[1]).min(verticies[2]);
+    let hi = verticies[0].max(verticies[1]).max(verticies[2]);
+    let min = ((lo - half + SUBPIXEL_SCALE - 1).div_euclid(I64Vec2::splat(SUBPIXEL_SCALE)))
+        .max(I64Vec2::ZERO);
+    let max =
+        ((hi - half).div_euclid(I64Vec2::splat(SUBPIXEL_SCALE))).min(dimensions.as_i64vec2() - 1);
+    if min.cmpgt(max).any() {
+        return;
+    }
+
+    // With the triangle wound so its area is positive, the inside of every edge is where its
+    // edge function is positive. Points exactly on an edge are kept only for top and left
+    // edges, by biasing the others down by one.
+    let edges = [(1, 2), (2, 0), (0, 1)].map(|(i, j)| {
+        let (a, b) = (verticies[i], verticies[j]);
+        let d = b - a;
+        let top_left = d.y < 0 || (d.y == 0 && d.x > 0);
+        (a, d, if top_left { 0 } else { -1 })
+    });
+    let start = min * SUBPIXEL_SCALE + half;
+    let mut row = edges.map(|(a, d, bias)| d.perp_dot(start - a) + bias);
+    // Moving one cell right changes an edge function by -dy, one cell down by dx
+    let step_x = edges.map(|(_, d, _)| -d.y * SUBPIXEL_SCALE);
+    let step_y = edges.map(|(_, d, _)| d.x * SUBPIXEL_SCALE);
+
+    for y in min.y..=max.y {
+        let mut w = row;
+        for x in min.x..=max.x {
+            if w[0] >= 0 && w[1] >= 0 && w[2] >= 0 {
+                visit(ivec2(x as i32, y as i32));
+            }
+            (0..3).for_each(|i| w[i] += step_x[i]);
+        }
+        (0..3).for_each(|i| row[i] += step_y[i]);
     }
 }
@@ -0,0 +1,142 @@
+//! Line glyphs picked from the direction of the line through a cell. Each glyph is described by
+//! the arms it has, so lines meeting in a cell merge into corner, junction and crossing glyphs.
+pub(crate) use super::*;
+
+/// The characters lines are drawn with when [`LineStyle::glyphs`](lines::LineStyle::glyphs) is
+/// set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphSet {
+    /// `-`, `|`, `/`, `\` with `+` and `X` where lines meet.
+    Ascii,
+    /// Unicode box drawing characters, with corners and junctions such as `┌`, `┴` and `┼`.
+    BoxDrawing,
+}
+
+/// Bit set of the directions a glyph's strokes leave its cell in.
+pub type Arms = u8;
+pub const UP: Arms = 1;
+pub const DOWN: Arms = 2;
+pub const LEFT: Arms = 4;
+pub const RIGHT: Arms = 8;
+/// A stroke from the bottom left to the top right corner, like `/`.
+pub const RISING: Arms = 16;
+/// A stroke from the top left to the bottom right corner, like `\`.
+pub const FALLING: Arms = 32;
+
+const HORIZONTAL: Arms = LEFT | RIGHT;
+const VERTICAL: Arms = UP | DOWN;
+const DIAGONAL: Arms = RISING | FALLING;
+
+/// Box drawing glyphs indexed by their orthogonal arms. Lone arms use half lines so they merge
+/// into corners rather than junctions.
+const BOX: [char; 16] = [
+    ' ', '╵', '╷', '│', '╴', '┘', '┐', '┤', '╶', '└', '┌', '├', '─', '┴', '┬', '┼',
+];
+
+impl GlyphSet {
+    pub fn glyph(&self, arms: Arms) -> char {
+        //! The glyph with the given arms. Glyphs can't show orthogonal and diagonal strokes
+        //! together, so those become crossings.
+        let orthogonal = arms & (HORIZONTAL | VERTICAL);
+        let diagonal = arms & DIAGONAL;
+        match self {
+            GlyphSet::Ascii => match (orthogonal, diagonal) {
+                (0, RISING) => '/',
+                (0, FALLING) => '\\',
+                (0, DIAGONAL) => 'X',
+                (0, _) => ' ',
+                (o, 0) if o & HORIZONTAL == o => '-',
+                (o, 0) if o & VERTICAL == o => '|',
+                _ => '+',
+            },
+            GlyphSet::BoxDrawing => match (orthogonal, diagonal) {
+                (0, RISING) => '╱',
+                (0, FALLING) => '╲',
+                (0, DIAGONAL) => '╳',
+                (0, _) => ' ',
+                (o, 0) => BOX[o as usize],
+                _ => '┼',
+            },
+        }
+    }
+    pub fn arms(&self, glyph: char) -> Option<Arms> {
+        //! The arms of a glyph from this set, `None` for any other character.
+        match self {
+            GlyphSet::Ascii => match glyph {
+                '-' => Some(HORIZONTAL),
+                '|' => Some(VERTICAL),
+                '/' => Some(RISING),
+                '\\' => Some(FALLING),
+                'X' => Some(DIAGONAL),
+                '+' => Some(HORIZONTAL | VERTICAL),
+                _ => None,
+            },
+            GlyphSet::BoxDrawing => match glyph {
+                '╱' => Some(RISING),
+                '╲' => Some(FALLING),
+                '╳' => Some(DIAGONAL),
+                ' ' => None,
+                c => BOX.iter().position(|b| *b == c).map(|i| i as Arms),
+            },
+        }
+    }
+}
+
+/// Arms of the cells along a line, split into the cells where it starts and ends and those in
+/// between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SegmentArms {
+    pub first: Arms,
+    pub middle: Arms,
+    pub last: Arms,
+}
+
+pub fn merge_arms(existing: Arms, new: Arms) -> Arms {
+    //! Arms of a cell where a stroke with `new` arms is drawn over one with `existing` arms.
+    //! Orthogonal and diagonal strokes can't share a glyph, so the new stroke replaces the old
+    //! one when they are mixed.
+    if (existing & DIAGONAL == 0) == (new & DIAGONAL == 0) {
+        existing | new
+    } else {
+        new
+    }
+}
+
+impl SegmentArms {
+    pub fn new(delta: Vec2) -> Self {
+        //! Snaps the direction `delta` to the nearest of the eight glyph directions. Cells are
+        //! printed two characters wide, so they are treated as square.
+        let steep = (delta.y / delta.x).abs();
+        let tan = std::f32::consts::FRAC_PI_8.tan();
+        let (forward, backward) = if delta == Vec2::ZERO || steep <= tan {
+            if delta.x >= 0.0 {
+                (RIGHT, LEFT)
+            } else {
+                (LEFT, RIGHT)
+            }
+        } else if steep >= 1.0 / tan {
+            if delta.y >= 0.0 {
+                (DOWN, UP)
+            } else {
+                (UP, DOWN)
+            }
+        } else {
+            // Screen y points down, so a line going right and down falls
+            let diagonal = if delta.x * delta.y > 0.0 {
+                FALLING
+            } else {
+                RISING
+            };
+            return Self {
+                first: diagonal,
+                middle: diagonal,
+                last: diagonal,
+            };
+        };
+        Self {
+            first: forward,
+            middle: forward | backward,
+            last: backward,
+        }
+    }
+}
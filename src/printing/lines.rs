@@ -0,0 +1,302 @@
+//! Styled line drawing. Lines are given in continuous cell coordinates like
+//! [`CharBuffer::draw_triangle_subpixel`], so a line through the centers of a row of cells runs
+//! along `y + 0.5`.
+pub(crate) use super::*;
+use drawing::{DrawError, Shade};
+use glam::Vec2Swizzles;
+use glyphs::{merge_arms, GlyphSet, SegmentArms};
+
+/// Which stretches of a line are drawn, measured in cells along the line. Lines up to a cell
+/// thick count the cells stepped along their major axis instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinePattern {
+    Solid,
+    Dashed {
+        dash: f32,
+        gap: f32,
+    },
+    /// Single cell dots `gap` cells apart.
+    Dotted {
+        gap: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    /// Width of the line in cells.
+    pub thickness: f32,
+    pub pattern: LinePattern,
+    /// Blends the shaded color into each cell by how much of it the line covers, rather than
+    /// covering cells all or nothing.
+    pub anti_aliased: bool,
+    /// Replaces the characters shaded along the line with glyphs following its direction,
+    /// merged with the glyphs of lines already drawn where they meet. Glyph lines are always one
+    /// cell wide and aliased.
+    pub glyphs: Option<GlyphSet>,
+}
+
+impl LinePattern {
+    fn is_drawn(&self, along: f32) -> bool {
+        //! Whether the point `along` cells from the start of the line is on a dash.
+        let (on, off) = match *self {
+            LinePattern::Solid => return true,
+            LinePattern::Dashed { dash, gap } => (dash, gap),
+            LinePattern::Dotted { gap } => (1.0, gap),
+        };
+        let period = on + off;
+        period <= 0.0 || along.rem_euclid(period) < on
+    }
+}
+
+#[buildstructor]
+impl LineStyle {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        thickness: Option<f32>,
+        pattern: Option<LinePattern>,
+        anti_aliased: Option<bool>,
+        glyphs: Option<GlyphSet>,
+    ) -> Self {
+        Self {
+            thickness: thickness.unwrap_or(1.0),
+            pattern: pattern.unwrap_or(LinePattern::Solid),
+            anti_aliased: anti_aliased.unwrap_or(false),
+            glyphs,
+        }
+    }
+}
+
+impl std::default::Default for LineStyle {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl CharBuffer {
+    pub fn draw_line_styled(
+        &mut self,
+        start: Vec2,
+        end: Vec2,
+        style: &LineStyle,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Draws a line between two points in continuous cell coordinates. Lines up to a cell
+        //! wide are stepped along their major axis, using Xiaolin Wu's algorithm when
+        //! anti-aliased. Thicker lines cover every cell whose center is within half the thickness
+        //! of the line, with rounded ends.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::printing::lines::{LinePattern, LineStyle};
+        //! let mut cb = CharBuffer::new(uvec2(20, 5), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! let style = LineStyle::builder()
+        //!     .pattern(LinePattern::Dashed { dash: 3.0, gap: 2.0 })
+        //!     .build();
+        //! let red = RgbColor(255, 0, 0);
+        //! cb.draw_line_styled(vec2(0.5, 2.5), vec2(19.5, 2.5), &style, |_, _| {
+        //!     (Some('.'), Some(red))
+        //! })
+        //! .unwrap();
+        //! let row = cb.row(2).unwrap().iter().map(|c| c.char).collect::<String>();
+        //! assert_eq!(row, "...  ...  ...  ...  ");
+        //! // Only the part of a line over the buffer is stepped through
+        //! let far = 1e9;
+        //! cb.draw_line_styled(vec2(-far, 0.5), vec2(far, 0.5), &LineStyle::default(), |_, _| {
+        //!     (Some('#'), None)
+        //! })
+        //! .unwrap();
+        //! assert!(cb.row(0).unwrap().iter().all(|c| c.char == '#'));
+        //! ```
+        self.draw_polyline(&[start, end], false, style, shading)
+    }
+    pub fn draw_polyline(
+        &mut self,
+        points: &[Vec2],
+        closed: bool,
+        style: &LineStyle,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Draws lines between consecutive points, and back to the first point when `closed`.
+        //! Patterns carry on from one segment to the next.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::printing::{glyphs::GlyphSet, lines::LineStyle};
+        //! let mut cb = CharBuffer::new(uvec2(4, 3), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! let style = LineStyle::builder().glyphs(GlyphSet::BoxDrawing).build();
+        //! let corners = [vec2(0.5, 0.5), vec2(3.5, 0.5), vec2(3.5, 2.5), vec2(0.5, 2.5)];
+        //! cb.draw_polyline(&corners, true, &style, |_, _| (Some('#'), None)).unwrap();
+        //! let rows = cb.rows().map(|r| r.iter().map(|c| c.char).collect::<String>());
+        //! assert_eq!(rows.collect::<Vec<_>>(), ["┌──┐", "│  │", "└──┘"]);
+        //! ```
+        let closing = (closed && points.len() > 2).then(|| (points[points.len() - 1], points[0]));
+        let segments = points.windows(2).map(|w| (w[0], w[1])).chain(closing);
+        let thin = style.thickness <= 1.0 || style.glyphs.is_some();
+        let anti_aliased = style.anti_aliased && style.glyphs.is_none();
+        let mut offset = 0.0;
+        let mut last = None;
+        for (start, end) in segments {
+            let arms = SegmentArms::new(end - start);
+            let (first_cell, last_cell) = (start.floor().as_ivec2(), end.floor().as_ivec2());
+            let mut plot = |buffer: &mut CharBuffer, p: IVec2, coverage: f32, along: f32| {
+                if coverage <= 0.0
+                    || !buffer.is_valid_point(p)
+                    || !style.pattern.is_drawn(offset + along)
+                {
+                    return;
+                }
+                // Segments share their end points, which aliased lines would shade twice. Glyphs
+                // merge there instead.
+                if style.glyphs.is_none() && thin && !anti_aliased && last.replace(p) == Some(p) {
+                    return;
+                }
+                let mut shade = shading(p, buffer);
+                if let Some(glyphs) = style.glyphs {
+                    // Lines only reach into their end cells from one side, so lines meeting at
+                    // an end form a corner rather than a crossing
+                    let arms = match (p == first_cell, p == last_cell) {
+                        (true, false) => arms.first,
+                        (false, true) => arms.last,
+                        _ => arms.middle,
+                    };
+                    let existing = buffer
+                        .get_char(p.as_uvec2())
+                        .and_then(|(c, _)| glyphs.arms(c))
+                        .unwrap_or(0);
+                    shade.0 = shade.0.map(|_| glyphs.glyph(merge_arms(existing, arms)));
+                }
+                buffer.blend_shade(p.as_uvec2(), shade, coverage);
+            };
+            offset += if thin {
+                self.thin_line(start, end, anti_aliased, &mut plot)
+            } else {
+                self.thick_line(start, end, style, &mut plot)
+            };
+        }
+        Ok(())
+    }
+    fn thin_line(
+        &mut self,
+        start: Vec2,
+        end: Vec2,
+        anti_aliased: bool,
+        plot: &mut impl FnMut(&mut CharBuffer, IVec2, f32, f32),
+    ) -> f32 {
+        //! Returns how many cells the line steps along its major axis.
+        // Shifted so cell centers are at whole numbers
+        let (mut a, mut b) = (start - 0.5, end - 0.5);
+        let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+        // Step along x, swapping the axes of steep lines
+        let swap = |v: Vec2| if steep { v.yx() } else { v };
+        let cell = |major: f32, minor: f32| {
+            let p = ivec2(major as i32, minor as i32);
+            if steep {
+                p.yx()
+            } else {
+                p
+            }
+        };
+        (a, b) = (swap(a), swap(b));
+        let origin = a.x.round();
+        if a.x > b.x {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let delta = b - a;
+        let gradient = if delta.x == 0.0 {
+            0.0
+        } else {
+            delta.y / delta.x
+        };
+
+        let (first, last) = (a.x.round(), b.x.round());
+        // Only step through the cells a line a cell wide around it could touch
+        let size = swap(self.dimensions.as_vec2());
+        let (mut low, mut high) = (first.max(-1.0), last.min(size.x));
+        if gradient != 0.0 {
+            let enter = a.x + (-1.0 - a.y) / gradient;
+            let exit = a.x + (size.y - a.y) / gradient;
+            low = low.max(enter.min(exit).floor());
+            high = high.min(enter.max(exit).ceil());
+        } else if !(-1.0..=size.y).contains(&a.y) {
+            high = low - 1.0;
+        }
+        let steps = if high >= low {
+            (high - low) as u32 + 1
+        } else {
+            0
+        };
+        for step in 0..steps {
+            let x = low + step as f32;
+            let y = a.y + gradient * (x - a.x);
+            // Thin lines measure patterns in cells stepped, which keeps them evenly spaced
+            let along = (x - origin).abs();
+            if anti_aliased {
+                // Cells at the ends are only partly covered along the major axis
+                let coverage = (b.x.min(x + 0.5) - a.x.max(x - 0.5)).clamp(0.0, 1.0);
+                let (below, fraction) = (y.floor(), y - y.floor());
+                plot(self, cell(x, below), coverage * (1.0 - fraction), along);
+                plot(self, cell(x, below + 1.0), coverage * fraction, along);
+            } else {
+                plot(self, cell(x, y.round()), 1.0, along);
+            }
+        }
+        last - first
+    }
+    fn thick_line(
+        &mut self,
+        start: Vec2,
+        end: Vec2,
+        style: &LineStyle,
+        plot: &mut impl FnMut(&mut CharBuffer, IVec2, f32, f32),
+    ) -> f32 {
+        //! Returns the length of the line.
+        let radius = style.thickness / 2.0;
+        let delta = end - start;
+        let length_squared = delta.length_squared();
+        let min = (start.min(end) - radius - 1.0)
+            .floor()
+            .as_ivec2()
+            .max(IVec2::ZERO);
+        let max = (start.max(end) + radius + 1.0)
+            .ceil()
+            .as_ivec2()
+            .min(self.dimensions.as_ivec2() - IVec2::ONE);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let center = vec2(x as f32, y as f32) + 0.5;
+                let t = if length_squared > 0.0 {
+                    ((center - start).dot(delta) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = center.distance(start + delta * t);
+                let coverage = if style.anti_aliased {
+                    (radius + 0.5 - distance).clamp(0.0, 1.0)
+                } else if distance <= radius {
+                    1.0
+                } else {
+                    0.0
+                };
+                plot(self, ivec2(x, y), coverage, t * length_squared.sqrt());
+            }
+        }
+        length_squared.sqrt()
+    }
+    pub(crate) fn blend_shade(&mut self, position: UVec2, shade: Shade, coverage: f32) {
+        //! Writes `shade` to a cell, mixing its color with the cell's current color by
+        //! `coverage`. The character is written regardless of coverage.
+        let Some(cell) = self.get_mut(position) else {
+            return;
+        };
+        if let Some(char) = shade.0 {
+            cell.char = char;
+        }
+        if let Some(color) = shade.1 {
+            let under = cell.bg.unwrap_or(cell.fg);
+            cell.fg = mix_colors(under, color, coverage.min(1.0));
+        }
+    }
+}
+
+pub(crate) fn mix_colors(a: RgbColor, b: RgbColor, t: f32) -> RgbColor {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    RgbColor(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
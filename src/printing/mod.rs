@@ -1,16 +1,29 @@
 pub(crate) use super::*;
 
 pub mod drawing;
+pub mod glyphs;
+pub mod lines;
+pub mod shapes;
 
-#[derive(Clone, Getters, MutGetters, Setters)]
+/// A single character cell of a [`CharBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub char: char,
+    pub fg: RgbColor,
+    /// Background color, `None` keeps the terminal's own background. Cells holding '.' without
+    /// a background are filled with `fg`.
+    pub bg: Option<RgbColor>,
+    pub effects: Effects,
+}
+
+/// Cells stored row by row in a single contiguous array.
+#[derive(Clone, Getters, Setters)]
 pub struct CharBuffer {
     #[getset(get = "pub")]
-    value: Vec<Vec<char>>, // Vec < Row >
-    #[getset(get = "pub")]
-    colors: Vec<Vec<RgbColor>>,
+    cells: Vec<Cell>,
 
     #[getset(get = "pub")]
-    dimensions: UVec2, // Rows, Columns
+    dimensions: UVec2, // Columns, Rows
 }
 
 #[derive(Debug, Error)]
@@ -23,12 +36,55 @@ pub enum CharBufferError {
     UsizeConversion,
 }
 
+/// Width over height of a [`CharBuffer`] cell, which is printed two characters wide, in fonts
+/// whose characters are twice as tall as they are wide.
+pub const DEFAULT_CELL_ASPECT: f32 = 1.0;
+
+pub fn terminal_cell_aspect() -> Option<f32> {
+    //! Width over height of a [`CharBuffer`] cell as printed in the current terminal, from the
+    //! window size it reports in pixels. `None` when it doesn't report one, as many don't.
+    let size = crossterm::terminal::window_size().ok()?;
+    if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+        return None;
+    }
+    let char_width = size.width as f32 / size.columns as f32;
+    let char_height = size.height as f32 / size.rows as f32;
+    Some(2.0 * char_width / char_height)
+}
+
+impl Cell {
+    pub fn new(char: char, fg: RgbColor) -> Self {
+        Self {
+            char,
+            fg,
+            bg: None,
+            effects: Effects::new(),
+        }
+    }
+    pub fn style(&self) -> Style {
+        let bg = match (self.bg, self.char) {
+            (Some(bg), _) => Some(bg),
+            (None, '.') => Some(self.fg),
+            (None, _) => None,
+        };
+        Style::new()
+            .fg_color(Some(Color::Rgb(self.fg)))
+            .bg_color(bg.map(Color::Rgb))
+            .effects(self.effects)
+    }
+}
+
 impl CharBuffer {
     #[inline]
     pub fn is_valid_point(&self, point: IVec2) -> bool {
         (!point.x.is_negative() && !point.y.is_negative())
             && (point.x < self.dimensions.x as i32 && point.y < self.dimensions.y as i32)
     }
+    #[inline]
+    fn index(&self, position: UVec2) -> Option<usize> {
+        (position.x < self.dimensions.x && position.y < self.dimensions.y)
+            .then(|| position.y as usize * self.dimensions.x as usize + position.x as usize)
+    }
     pub fn set_char(
         &mut self,
         position: UVec2,
@@ -41,145 +97,164 @@ impl CharBuffer {
         //! ```
         //! pub use text_3d_graphics::printing::CharBuffer;
         //! pub use text_3d_graphics::prelude::*;
-        //! let mut cb = CharBuffer::from_str("   \n @ \n  +\n",RgbColor(255,255,255)).unrwap();
-        //! cb.set_char(uvec2(2,1),Some('*'),None);
-        //! assert_eq!("   \n @ \n *+\n",cb.to_string());
+        //! let mut cb = CharBuffer::new(uvec2(3, 3), ' ', RgbColor(255, 255, 255)).unwrap();
+        //! cb.set_char(uvec2(1, 2), Some('*'), None).unwrap();
+        //! assert_eq!(cb.get_char(uvec2(1, 2)), Some(('*', RgbColor(255, 255, 255))));
+        //! assert!(cb.set_char(uvec2(3, 0), Some('*'), None).is_err());
         //! ```
-        let report = || {
+        let dimensions = self.dimensions;
+        let cell = self.get_mut(position).ok_or_else(|| {
             Report::new(CharBufferError::OutOfBounds {
                 attempt: position,
-                dimensions: self.dimensions,
+                dimensions,
             })
-        };
-
-        let x = TryInto::<usize>::try_into(position.x)
-            .change_context_lazy(|| CharBufferError::UsizeConversion)
-            .attach_printable_lazy(|| "u32 -> usize")?;
-        let y = TryInto::<usize>::try_into(position.y)
-            .change_context_lazy(|| CharBufferError::UsizeConversion)
-            .attach_printable_lazy(|| "u32 -> usize")?;
-
+        })?;
         if let Some(char) = char {
-            *(self
-                .value
-                .get_mut(y)
-                .ok_or(report())?
-                .get_mut(x)
-                .ok_or(report())?) = char;
+            cell.char = char;
         }
         if let Some(color) = color {
-            (*self
-                .colors
-                .get_mut(y)
-                .ok_or(report())?
-                .get_mut(x)
-                .ok_or(report())?) = color
+            cell.fg = color;
         }
-
         Ok(())
     }
+    #[inline]
+    pub fn get(&self, position: UVec2) -> Option<&Cell> {
+        self.index(position).map(|i| &self.cells[i])
+    }
+    #[inline]
+    pub fn get_mut(&mut self, position: UVec2) -> Option<&mut Cell> {
+        self.index(position).map(|i| &mut self.cells[i])
+    }
+    /// The cell at `position` without bounds checking.
+    ///
+    /// # Safety
+    /// `position` must be within [`dimensions`](CharBuffer::dimensions).
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, position: UVec2) -> &mut Cell {
+        let i = position.y as usize * self.dimensions.x as usize + position.x as usize;
+        self.cells.get_unchecked_mut(i)
+    }
+    pub fn cells_mut(&mut self) -> &mut [Cell] {
+        //! Every cell row by row. Handed out as a slice so the number of cells always matches
+        //! the dimensions, which the unchecked accessors rely on.
+        &mut self.cells
+    }
+    pub fn row(&self, y: u32) -> Option<&[Cell]> {
+        let width = self.dimensions.x as usize;
+        (y < self.dimensions.y).then(|| &self.cells[y as usize * width..][..width])
+    }
+    pub fn row_mut(&mut self, y: u32) -> Option<&mut [Cell]> {
+        let width = self.dimensions.x as usize;
+        (y < self.dimensions.y).then(|| &mut self.cells[y as usize * width..][..width])
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks_exact(self.dimensions.x.max(1) as usize)
+    }
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Cell]> {
+        self.cells
+            .chunks_exact_mut(self.dimensions.x.max(1) as usize)
+    }
+    pub fn positions(&self) -> impl Iterator<Item = (UVec2, &Cell)> {
+        //! Every cell along with its position, row by row.
+        let width = self.dimensions.x.max(1);
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, c)| (uvec2(i as u32 % width, i as u32 / width), c))
+    }
     pub fn fill(&mut self, char: char, color: RgbColor) {
-        self.value
-            .iter_mut()
-            .map(|v| v.iter_mut())
-            .flatten()
-            .zip(self.colors.iter_mut().map(|v| v.iter_mut()).flatten())
-            .for_each(|(ch, co)| {
-                *ch = char;
-                *co = color;
-            });
+        self.cells.fill(Cell::new(char, color));
     }
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
-        let reset = format!("{}", anstyle::Reset);
-        self.value
-            .iter()
-            .zip(self.colors.iter())
-            .map(|(row, colors)| {
-                let mut val = row
-                    .iter()
-                    .zip(colors.iter())
-                    .map(|(char, color)| {
-                        let style = anstyle::Style::new()
-                            .fg_color(Some(anstyle::Color::Rgb(*color)))
-                            .bg_color(if *char == '.' {
-                                Some(anstyle::Color::Rgb(*color))
-                            } else {
-                                None
-                            });
-                        format!("{}{}{}{}", reset, style, char, char)
-                    })
-                    .collect::<String>();
-                val.push_str(&reset);
-                val.push('\n');
-                val
-            })
-            .collect::<String>()
+        format!("{self}")
     }
     pub fn new(dimensions: UVec2, char: char, color: RgbColor) -> Result<Self, CharBufferError> {
         Ok(Self {
-            value: vec![
-                vec![
-                    char;
-                    TryInto::<usize>::try_into(dimensions.x)
-                        .change_context_lazy(|| CharBufferError::UsizeConversion)
-                        .attach_printable_lazy(|| "u32 -> usize")?
-                ];
-                TryInto::<usize>::try_into(dimensions.y)
-                    .change_context_lazy(|| CharBufferError::UsizeConversion)
-                    .attach_printable_lazy(|| "u32 -> usize")?
-            ],
-            colors: vec![
-                vec![
-                    color;
-                    TryInto::<usize>::try_into(dimensions.x)
-                        .change_context_lazy(|| CharBufferError::UsizeConversion)
-                        .attach_printable_lazy(|| "u32 -> usize")?
-                ];
-                TryInto::<usize>::try_into(dimensions.y)
-                    .change_context_lazy(|| CharBufferError::UsizeConversion)
-                    .attach_printable_lazy(|| "u32 -> usize")?
-            ],
+            cells: vec![Cell::new(char, color); cell_count(dimensions)?],
             dimensions,
         })
     }
     pub fn get_char(&self, pos: UVec2) -> Option<(char, RgbColor)> {
-        if self.is_valid_point(ivec2(pos.x as i32, pos.y as i32)) {
-            return Some((
-                *self
-                    .value
-                    .get(pos.y as usize)
-                    .unwrap()
-                    .get(pos.x as usize)
-                    .unwrap(),
-                *self
-                    .colors
-                    .get(pos.y as usize)
-                    .unwrap()
-                    .get(pos.x as usize)
-                    .unwrap(),
-            ));
-        }
-        None
+        self.get(pos).map(|c| (c.char, c.fg))
+    }
+    #[deprecated(note = "cells are stored in one array now, use `rows` or `cells`")]
+    pub fn value(&self) -> Vec<Vec<char>> {
+        //! A copy of the characters, row by row.
+        //! ```
+        //! # #![allow(deprecated)]
+        //! use text_3d_graphics::prelude::*;
+        //! let mut buffer = CharBuffer::new(uvec2(2, 1), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! buffer.set_char(uvec2(1, 0), Some('#'), Some(RgbColor(255, 0, 0))).unwrap();
+        //! buffer.set_dimensions(uvec2(3, 2), '+', RgbColor(0, 0, 255));
+        //! assert_eq!(buffer.value(), [vec![' ', '#', '+'], vec!['+', '+', '+']]);
+        //! assert_eq!(buffer.colors()[0][1], RgbColor(255, 0, 0));
+        //! assert_eq!(buffer.colors()[1][0], RgbColor(0, 0, 255));
+        //! ```
+        self.rows()
+            .map(|row| row.iter().map(|c| c.char).collect())
+            .collect()
+    }
+    #[deprecated(note = "cells are stored in one array now, use `rows` or `cells`")]
+    pub fn colors(&self) -> Vec<Vec<RgbColor>> {
+        //! A copy of the foreground colors, row by row.
+        self.rows()
+            .map(|row| row.iter().map(|c| c.fg).collect())
+            .collect()
     }
     pub fn set_dimensions(&mut self, dimensions: UVec2, character: char, color: RgbColor) {
-        self.value.resize(
-            dimensions.x as usize,
-            vec![character; dimensions.y as usize],
-        );
-        self.value
-            .iter_mut()
-            .for_each(|v| v.resize(dimensions.y as usize, character));
-        self.colors
-            .resize(dimensions.x as usize, vec![color; dimensions.y as usize]);
-        self.colors
-            .iter_mut()
-            .for_each(|v| v.resize(dimensions.y as usize, color));
+        //! Resizes the buffer, keeping the cells that are still in range and filling new ones
+        //! with `character` and `color`. Panics if the new number of cells doesn't fit in a
+        //! `usize`, see [`CharBuffer::new`] for a fallible constructor.
+        let mut resized =
+            Self::new(dimensions, character, color).unwrap_or_else(|e| panic!("{:?}", e));
+        resized.blit(self, UVec2::ZERO);
+        *self = resized;
     }
+    pub fn region(&self, origin: UVec2, dimensions: UVec2) -> Self {
+        //! A copy of the cells in the `dimensions` sized rectangle at `origin`, cut short where
+        //! it extends past this buffer.
+        let end = (origin + dimensions).min(self.dimensions);
+        let origin = origin.min(end);
+        let columns = origin.x as usize..end.x as usize;
+        Self {
+            cells: (origin.y..end.y)
+                .flat_map(|y| self.row(y).unwrap()[columns.clone()].iter().copied())
+                .collect(),
+            dimensions: end - origin,
+        }
+    }
+    pub fn blit(&mut self, region: &CharBuffer, origin: UVec2) {
+        //! Copies every cell of `region` into this buffer with its top left corner at `origin`.
+        //! Cells landing outside this buffer are dropped.
+        let x = (origin.x as usize).min(self.dimensions.x as usize);
+        for (row, src) in self.rows_mut().skip(origin.y as usize).zip(region.rows()) {
+            let len = src.len().min(row.len() - x);
+            row[x..x + len].copy_from_slice(&src[..len]);
+        }
+    }
+}
+
+/// Number of cells in a `dimensions` sized buffer, if it fits in a `usize`.
+fn cell_count(dimensions: UVec2) -> Result<usize, CharBufferError> {
+    TryInto::<usize>::try_into(dimensions.x)
+        .ok()
+        .zip(TryInto::<usize>::try_into(dimensions.y).ok())
+        .and_then(|(x, y)| x.checked_mul(y))
+        .ok_or_else(|| Report::new(CharBufferError::UsizeConversion))
+        .attach_printable_lazy(|| "u32 -> usize")
 }
 
 impl std::fmt::Display for CharBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        let reset = anstyle::Reset;
+        for row in self.rows() {
+            for cell in row {
+                write!(f, "{}{}{}{}", reset, cell.style(), cell.char, cell.char)?;
+            }
+            writeln!(f, "{}", reset)?;
+        }
+        Ok(())
     }
 }
 
@@ -0,0 +1,431 @@
+//! 2D shapes for drawing overlays on top of renders. Integer shapes are given in cells, while
+//! paths and polygons use continuous cell coordinates like [`CharBuffer::draw_line_styled`] and
+//! fill the cells whose centers they contain.
+pub(crate) use super::*;
+use drawing::{DrawError, Shade};
+use lines::LineStyle;
+
+/// How the inside of a self intersecting or multi contour shape is decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+    /// Inside where a ray from the point crosses the outline an odd number of times.
+    #[default]
+    EvenOdd,
+    /// Inside where the outline winds around the point at least once.
+    NonZero,
+}
+
+/// A shape made of straight and curved contours. Curves are flattened into line segments as
+/// they are added.
+/// ```
+/// use text_3d_graphics::prelude::*;
+/// use text_3d_graphics::printing::lines::LineStyle;
+/// use text_3d_graphics::printing::shapes::{FillRule, Path};
+/// let mut cb = CharBuffer::new(uvec2(20, 10), ' ', RgbColor(0, 0, 0)).unwrap();
+/// let mut path = Path::new();
+/// path.move_to(vec2(1.0, 9.0))
+///     .quadratic_to(vec2(10.0, -8.0), vec2(19.0, 9.0))
+///     .close();
+/// cb.fill_path(&path, FillRule::NonZero, |_, _| (Some('.'), None)).unwrap();
+/// cb.stroke_path(&path, &LineStyle::default(), |_, _| (Some('#'), None)).unwrap();
+/// assert_eq!(cb.get_char(uvec2(10, 5)).unwrap().0, '.');
+/// assert_eq!(cb.get_char(uvec2(10, 0)).unwrap().0, '#');
+/// assert_eq!(cb.get_char(uvec2(0, 0)).unwrap().0, ' ');
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    contours: Vec<Contour>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Contour {
+    points: Vec<Vec2>,
+    closed: bool,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn move_to(&mut self, point: Vec2) -> &mut Self {
+        //! Starts a new contour at `point`.
+        self.contours.push(Contour {
+            points: vec![point],
+            closed: false,
+        });
+        self
+    }
+    fn current(&mut self) -> &mut Contour {
+        if !matches!(self.contours.last(), Some(c) if !c.closed) {
+            // Contours continue from where the last one ended, or the origin
+            let start = self
+                .contours
+                .last()
+                .and_then(|c| c.points.first().copied())
+                .unwrap_or(Vec2::ZERO);
+            self.move_to(start);
+        }
+        self.contours.last_mut().unwrap()
+    }
+    fn last_point(&mut self) -> Vec2 {
+        *self.current().points.last().unwrap()
+    }
+    pub fn line_to(&mut self, point: Vec2) -> &mut Self {
+        self.current().points.push(point);
+        self
+    }
+    pub fn quadratic_to(&mut self, control: Vec2, end: Vec2) -> &mut Self {
+        let start = self.last_point();
+        let segments = curve_segments(&[start, control, end]);
+        let points = (1..=segments).map(|i| {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            start * (u * u) + control * (2.0 * u * t) + end * (t * t)
+        });
+        self.current().points.extend(points);
+        self
+    }
+    pub fn cubic_to(&mut self, control_a: Vec2, control_b: Vec2, end: Vec2) -> &mut Self {
+        let start = self.last_point();
+        let segments = curve_segments(&[start, control_a, control_b, end]);
+        let points = (1..=segments).map(|i| {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            start * (u * u * u)
+                + control_a * (3.0 * u * u * t)
+                + control_b * (3.0 * u * t * t)
+                + end * (t * t * t)
+        });
+        self.current().points.extend(points);
+        self
+    }
+    pub fn arc_to(&mut self, center: Vec2, radii: Vec2, end_angle: f32) -> &mut Self {
+        //! Continues along the ellipse around `center` through the current point, until
+        //! `end_angle` radians. Angles increase clockwise on screen, starting from +x.
+        let start = self.last_point();
+        let offset = (start - center) / radii.max(Vec2::splat(f32::EPSILON));
+        let start_angle = offset.y.atan2(offset.x);
+        let points = arc_points(center, radii, start_angle, end_angle);
+        self.current().points.extend(points.into_iter().skip(1));
+        self
+    }
+    pub fn close(&mut self) -> &mut Self {
+        //! Joins the end of the current contour back to its start.
+        self.current().closed = true;
+        self
+    }
+    pub fn polygon(points: &[Vec2]) -> Self {
+        Self {
+            contours: vec![Contour {
+                points: points.to_vec(),
+                closed: true,
+            }],
+        }
+    }
+    pub fn contours(&self) -> impl Iterator<Item = (&[Vec2], bool)> {
+        //! The flattened contours, with whether each one is closed.
+        self.contours
+            .iter()
+            .map(|c| (c.points.as_slice(), c.closed))
+    }
+}
+
+impl CharBuffer {
+    pub fn draw_rect(
+        &mut self,
+        origin: IVec2,
+        size: UVec2,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Outlines the `size` cells wide rectangle whose top left cell is `origin`.
+        if size.x == 0 || size.y == 0 {
+            return Ok(());
+        }
+        let max = origin + size.as_ivec2() - IVec2::ONE;
+        let top_bottom = (origin.x..=max.x).flat_map(|x| [ivec2(x, origin.y), ivec2(x, max.y)]);
+        let sides = (origin.y + 1..max.y).flat_map(|y| [ivec2(origin.x, y), ivec2(max.x, y)]);
+        let mut cells = top_bottom.chain(sides).collect::<Vec<_>>();
+        // Single row or column rectangles would shade their cells twice
+        cells.sort_by_key(|p| (p.y, p.x));
+        cells.dedup();
+        self.shade_cells(cells, &mut shading);
+        Ok(())
+    }
+    pub fn fill_rect(
+        &mut self,
+        origin: IVec2,
+        size: UVec2,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        let min = origin.max(IVec2::ZERO);
+        let max = (origin + size.as_ivec2()).min(self.dimensions.as_ivec2());
+        for y in min.y..max.y {
+            self.shade_span(y, min.x, max.x - 1, &mut shading);
+        }
+        Ok(())
+    }
+    pub fn draw_ellipse(
+        &mut self,
+        center: IVec2,
+        radii: UVec2,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Outlines an ellipse with the midpoint ellipse algorithm.
+        let mut cells = ellipse_quadrant(radii)
+            .into_iter()
+            .flat_map(|p| [p, ivec2(-p.x, p.y), ivec2(p.x, -p.y), -p])
+            .map(|p| center + p)
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|p| (p.y, p.x));
+        cells.dedup();
+        self.shade_cells(cells, &mut shading);
+        Ok(())
+    }
+    pub fn fill_ellipse(
+        &mut self,
+        center: IVec2,
+        radii: UVec2,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Fills the spans between the points of [`CharBuffer::draw_ellipse`]'s outline.
+        let mut half_widths = vec![0; radii.y as usize + 1];
+        for p in ellipse_quadrant(radii) {
+            let w = &mut half_widths[p.y as usize];
+            *w = (*w).max(p.x);
+        }
+        for (dy, half_width) in half_widths.iter().enumerate() {
+            let dy = dy as i32;
+            let (left, right) = (center.x - half_width, center.x + half_width);
+            self.shade_span(center.y + dy, left, right, &mut shading);
+            if dy != 0 {
+                self.shade_span(center.y - dy, left, right, &mut shading);
+            }
+        }
+        Ok(())
+    }
+    pub fn draw_circle(
+        &mut self,
+        center: IVec2,
+        radius: u32,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        self.draw_ellipse(center, UVec2::splat(radius), shading)
+    }
+    pub fn fill_circle(
+        &mut self,
+        center: IVec2,
+        radius: u32,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        self.fill_ellipse(center, UVec2::splat(radius), shading)
+    }
+    pub fn draw_arc(
+        &mut self,
+        center: Vec2,
+        radii: Vec2,
+        angles: std::ops::Range<f32>,
+        style: &LineStyle,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Draws the part of an ellipse between two angles in radians, which increase clockwise
+        //! on screen starting from +x.
+        let points = arc_points(center, radii, angles.start, angles.end);
+        self.draw_polyline(&points, false, style, shading)
+    }
+    pub fn fill_polygon(
+        &mut self,
+        points: &[Vec2],
+        rule: FillRule,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Fills a possibly concave or self intersecting polygon.
+        self.fill_contours(&[points], rule, shading)
+    }
+    pub fn fill_path(
+        &mut self,
+        path: &Path,
+        rule: FillRule,
+        shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Fills every contour of `path` together, so contours inside others make holes
+        //! depending on `rule`. Open contours are filled as if closed.
+        let contours = path
+            .contours()
+            .map(|(points, _)| points)
+            .collect::<Vec<_>>();
+        self.fill_contours(&contours, rule, shading)
+    }
+    pub fn stroke_path(
+        &mut self,
+        path: &Path,
+        style: &LineStyle,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        path.contours().try_for_each(|(points, closed)| {
+            self.draw_polyline(points, closed, style, &mut shading)
+        })
+    }
+    fn fill_contours(
+        &mut self,
+        contours: &[&[Vec2]],
+        rule: FillRule,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) -> Result<(), DrawError> {
+        //! Scanline fill sampling each cell at its center.
+        let edges = contours
+            .iter()
+            .filter(|c| c.len() > 2)
+            .flat_map(|c| {
+                c.iter()
+                    .zip(c.iter().cycle().skip(1))
+                    .map(|(a, b)| (*a, *b))
+            })
+            .filter(|(a, b)| a.y != b.y)
+            .collect::<Vec<_>>();
+        let Some((min_y, max_y)) = edges
+            .iter()
+            .flat_map(|(a, b)| [a.y, b.y])
+            .fold(None, |r: Option<(f32, f32)>, y| {
+                Some(r.map_or((y, y), |(lo, hi)| (lo.min(y), hi.max(y))))
+            })
+        else {
+            return Ok(());
+        };
+
+        let first = ((min_y - 0.5).ceil() as i32).max(0);
+        let last = ((max_y - 0.5).floor() as i32).min(self.dimensions.y as i32 - 1);
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        for y in first..=last {
+            let center = y as f32 + 0.5;
+            // Edges include their top end but not their bottom, so shared vertices count once
+            crossings.clear();
+            crossings.extend(edges.iter().filter_map(|(a, b)| {
+                let (top, bottom) = if a.y < b.y { (a, b) } else { (b, a) };
+                (top.y <= center && center < bottom.y).then(|| {
+                    let x = top.x + (center - top.y) * (bottom.x - top.x) / (bottom.y - top.y);
+                    (x, if a.y < b.y { 1 } else { -1 })
+                })
+            }));
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match rule {
+                    FillRule::EvenOdd => winding % 2 != 0,
+                    FillRule::NonZero => winding != 0,
+                };
+                if inside {
+                    // Cells whose centers are in `[left, right)`
+                    let left = (pair[0].0 - 0.5).ceil() as i32;
+                    let right = (pair[1].0 - 0.5).ceil() as i32 - 1;
+                    self.shade_span(y, left, right, &mut shading);
+                }
+            }
+        }
+        Ok(())
+    }
+    fn shade_span(
+        &mut self,
+        y: i32,
+        left: i32,
+        right: i32,
+        shading: &mut impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) {
+        //! Shades the cells from `left` to `right` inclusive on row `y`, skipping those outside
+        //! the buffer.
+        if y < 0 || y >= self.dimensions.y as i32 {
+            return;
+        }
+        let left = left.max(0);
+        let right = right.min(self.dimensions.x as i32 - 1);
+        for x in left..=right {
+            let p = ivec2(x, y);
+            let shade = shading(p, self);
+            // SAFETY: the span was clipped to the buffer
+            unsafe { self.apply_shade_unchecked(p.as_uvec2(), shade) };
+        }
+    }
+    fn shade_cells(
+        &mut self,
+        cells: impl IntoIterator<Item = IVec2>,
+        shading: &mut impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) {
+        for p in cells {
+            if self.is_valid_point(p) {
+                let shade = shading(p, self);
+                // SAFETY: checked by is_valid_point above
+                unsafe { self.apply_shade_unchecked(p.as_uvec2(), shade) };
+            }
+        }
+    }
+}
+
+fn ellipse_quadrant(radii: UVec2) -> Vec<IVec2> {
+    //! Points of the outline of an ellipse centered on the origin with `x, y >= 0`, walked by
+    //! the midpoint algorithm. Decision variables are scaled by 4 to stay in integers.
+    let (a, b) = (radii.x as i64, radii.y as i64);
+    if a == 0 || b == 0 {
+        let end = ivec2(a as i32, b as i32);
+        return (0..=end.x.max(end.y))
+            .map(|i| ivec2(i.min(end.x), i.min(end.y)))
+            .collect();
+    }
+    let (a2, b2) = (a * a, b * b);
+    let (mut x, mut y) = (0i64, b);
+    let (mut dx, mut dy) = (0, 2 * a2 * y);
+    let mut points = Vec::new();
+
+    // Region 1, where the outline is flatter than 45 degrees and x steps every point
+    let mut d = 4 * b2 - 4 * a2 * b + a2;
+    while dx < dy {
+        points.push(ivec2(x as i32, y as i32));
+        x += 1;
+        dx += 2 * b2;
+        if d < 0 {
+            d += 4 * (dx + b2);
+        } else {
+            y -= 1;
+            dy -= 2 * a2;
+            d += 4 * (dx - dy + b2);
+        }
+    }
+
+    // Region 2, where y steps every point
+    let mut d = b2 * (2 * x + 1) * (2 * x + 1) + 4 * a2 * (y - 1) * (y - 1) - 4 * a2 * b2;
+    while y >= 0 {
+        points.push(ivec2(x as i32, y as i32));
+        y -= 1;
+        dy -= 2 * a2;
+        if d > 0 {
+            d += 4 * (a2 - dy);
+        } else {
+            x += 1;
+            dx += 2 * b2;
+            d += 4 * (dx - dy + a2);
+        }
+    }
+    points
+}
+
+fn arc_points(center: Vec2, radii: Vec2, start: f32, end: f32) -> Vec<Vec2> {
+    //! Points along an elliptical arc, close enough together that the segments between them stay
+    //! within about a tenth of a cell of the curve.
+    let radius = radii.max_element().max(0.0);
+    let sweep = end - start;
+    let step = 2.0 * (1.0 - 0.1 / radius.max(0.1)).clamp(-1.0, 1.0).acos();
+    let segments = ((sweep.abs() / step.max(0.01)).ceil() as usize).clamp(1, 1024);
+    (0..=segments)
+        .map(|i| {
+            let angle = start + sweep * i as f32 / segments as f32;
+            center + Vec2::from_angle(angle) * radii
+        })
+        .collect()
+}
+
+fn curve_segments(control_points: &[Vec2]) -> usize {
+    //! How many segments to flatten a Bézier curve into, about one for each cell of its control
+    //! polygon's length.
+    let length: f32 = control_points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    (length.ceil() as usize).clamp(1, 1024)
+}
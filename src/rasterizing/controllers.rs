@@ -0,0 +1,250 @@
+//! Controllers that move a [`Camera`] each frame from the input gathered since the last one.
+//! Angles are in radians, with a yaw of 0 looking down +Z and positive pitch looking up.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::rasterizing::controllers::*;
+//! let mut camera = Rasterizer::default().camera;
+//! let mut orbit = OrbitController::builder().target(vec3(0.0, 0.0, 5.0)).distance(2.0).build();
+//! let mut input = ControllerInput::default();
+//! input.look = vec2(10.0, 0.0);
+//! orbit.update(&mut camera, &input, 0.1);
+//! assert!((camera.position().distance(vec3(0.0, 0.0, 5.0)) - 2.0).abs() < 1e-4);
+//! assert!(camera.look_dir().dot(vec3(0.0, 0.0, 5.0) - *camera.position()) > 0.0);
+//! ```
+pub(crate) use super::*;
+use crossterm::event::{Event, KeyCode, KeyEventKind, MouseEventKind};
+use std::ops::RangeInclusive;
+
+/// How close the pitch may come to straight up or down, where the up direction can't be kept.
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Input for a [`CameraController`], either filled in directly or from terminal events with
+/// [`ControllerInput::handle_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ControllerInput {
+    /// Movement relative to the camera, `x` right, `y` up and `z` forward, each from -1 to 1.
+    pub movement: Vec3,
+    /// How far the mouse moved in cells, with rows counting down.
+    pub look: Vec2,
+    /// Scroll steps, positive zooms in.
+    pub zoom: f32,
+    last_mouse: Option<IVec2>,
+}
+
+pub trait CameraController {
+    /// Moves `camera` by `input` over `delta` seconds.
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta: f32);
+}
+
+/// Circles a target, turned by the mouse and zoomed by scrolling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Radians turned per cell the mouse moves.
+    pub sensitivity: f32,
+    /// Fraction of the distance each scroll step zooms in by.
+    pub zoom_speed: f32,
+    pub distance_range: RangeInclusive<f32>,
+}
+
+/// First person flight, moved with WASD along the look direction and turned by the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlyController {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units moved per second.
+    pub speed: f32,
+    /// Radians turned per cell the mouse moves.
+    pub sensitivity: f32,
+}
+
+/// An orbit that keeps turning around its target at a steady rate, like an object on a
+/// turntable. The mouse only turns it sideways.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurntableController {
+    pub orbit: OrbitController,
+    /// Radians turned per second.
+    pub angular_speed: f32,
+}
+
+impl ControllerInput {
+    pub fn handle_event(&mut self, event: &Event) {
+        //! Updates the input from a terminal event. W, A, S and D move forward, left, back and
+        //! right, E and Q up and down, dragging the mouse looks around and scrolling zooms.
+        //!
+        //! Keys count as held until released. Terminals only report releases when crossterm's
+        //! keyboard enhancement flags are pushed, otherwise call
+        //! [`release_keys`](ControllerInput::release_keys) every frame.
+        match event {
+            Event::Key(key) => {
+                let held = if key.kind == KeyEventKind::Release {
+                    0.0
+                } else {
+                    1.0
+                };
+                if let KeyCode::Char(c) = key.code {
+                    match c.to_ascii_lowercase() {
+                        'w' => self.movement.z = held,
+                        's' => self.movement.z = -held,
+                        'd' => self.movement.x = held,
+                        'a' => self.movement.x = -held,
+                        'e' => self.movement.y = held,
+                        'q' => self.movement.y = -held,
+                        _ => {}
+                    }
+                }
+            }
+            Event::Mouse(mouse) => {
+                // Cells are printed two columns wide
+                let position = ivec2(mouse.column as i32 / 2, mouse.row as i32);
+                match mouse.kind {
+                    MouseEventKind::Drag(_) => {
+                        if let Some(last) = self.last_mouse {
+                            self.look += (position - last).as_vec2();
+                        }
+                        self.last_mouse = Some(position);
+                    }
+                    MouseEventKind::Down(_) | MouseEventKind::Moved => {
+                        self.last_mouse = Some(position)
+                    }
+                    MouseEventKind::ScrollUp => self.zoom += 1.0,
+                    MouseEventKind::ScrollDown => self.zoom -= 1.0,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    pub fn end_frame(&mut self) {
+        //! Clears the mouse movement and scrolling a frame has used up.
+        self.look = Vec2::ZERO;
+        self.zoom = 0.0;
+    }
+    pub fn release_keys(&mut self) {
+        self.movement = Vec3::ZERO;
+    }
+}
+
+#[buildstructor]
+impl OrbitController {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        target: Option<Vec3>,
+        distance: Option<f32>,
+        yaw: Option<f32>,
+        pitch: Option<f32>,
+        sensitivity: Option<f32>,
+        zoom_speed: Option<f32>,
+        distance_range: Option<RangeInclusive<f32>>,
+    ) -> Self {
+        Self {
+            target: target.unwrap_or(Vec3::ZERO),
+            distance: distance.unwrap_or(5.0),
+            yaw: yaw.unwrap_or(0.0),
+            pitch: pitch.unwrap_or(0.0),
+            sensitivity: sensitivity.unwrap_or(0.05),
+            zoom_speed: zoom_speed.unwrap_or(0.1),
+            distance_range: distance_range.unwrap_or(0.1..=f32::INFINITY),
+        }
+    }
+}
+
+impl std::default::Default for OrbitController {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, _delta: f32) {
+        self.yaw -= input.look.x * self.sensitivity;
+        self.pitch =
+            (self.pitch - input.look.y * self.sensitivity).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(input.zoom))
+            .clamp(*self.distance_range.start(), *self.distance_range.end());
+
+        *camera.position_mut() = self.target - direction(self.yaw, self.pitch) * self.distance;
+        *camera.up_dir_mut() = Vec3::Y;
+        camera.look_at(self.target);
+    }
+}
+
+#[buildstructor]
+impl FlyController {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        yaw: Option<f32>,
+        pitch: Option<f32>,
+        speed: Option<f32>,
+        sensitivity: Option<f32>,
+    ) -> Self {
+        Self {
+            yaw: yaw.unwrap_or(0.0),
+            pitch: pitch.unwrap_or(0.0),
+            speed: speed.unwrap_or(2.0),
+            sensitivity: sensitivity.unwrap_or(0.05),
+        }
+    }
+}
+
+impl std::default::Default for FlyController {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta: f32) {
+        self.yaw -= input.look.x * self.sensitivity;
+        self.pitch =
+            (self.pitch - input.look.y * self.sensitivity).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+
+        let forward = direction(self.yaw, self.pitch);
+        // The view's x axis, as `Mat4::look_to_rh` builds it
+        let right = forward.cross(Vec3::Y).normalize();
+        let movement =
+            right * input.movement.x + Vec3::Y * input.movement.y + forward * input.movement.z;
+        *camera.position_mut() += movement * self.speed * delta;
+        *camera.look_dir_mut() = forward;
+        *camera.up_dir_mut() = Vec3::Y;
+    }
+}
+
+#[buildstructor]
+impl TurntableController {
+    #[builder(visibility = "pub")]
+    pub fn new(orbit: Option<OrbitController>, angular_speed: Option<f32>) -> Self {
+        Self {
+            orbit: orbit.unwrap_or_default(),
+            angular_speed: angular_speed.unwrap_or(0.5),
+        }
+    }
+}
+
+impl std::default::Default for TurntableController {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl CameraController for TurntableController {
+    fn update(&mut self, camera: &mut Camera, input: &ControllerInput, delta: f32) {
+        self.orbit.yaw += self.angular_speed * delta;
+        let input = ControllerInput {
+            look: vec2(input.look.x, 0.0),
+            ..*input
+        };
+        self.orbit.update(camera, &input, delta);
+    }
+}
+
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    vec3(
+        yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    )
+}
@@ -0,0 +1,627 @@
+//! Mesh level draw calls. Drawing runs in two stages: the vertex stage transforms, culls, lights
+//! and projects every triangle of a mesh into buffer space, then the raster stage depth tests and
+//! fills the cells each projected triangle covers.
+pub(crate) use super::*;
+use crate::printing::drawing::{to_fixed, SUBPIXEL_BITS};
+use crate::printing::lines::mix_colors;
+use edges::NormalBuffer;
+use fog::{DepthCue, Fog};
+use lighting::{lit_color, Light, Lighting, ShadingMode};
+use picking::{CellId, IdBuffer};
+use shadows::ShadowMap;
+
+/// Fraction of a fragment's distance from the camera an edge may lie behind the surface it is
+/// drawn on in [`PolygonMode::HiddenLine`], so edges aren't hidden by their own triangles.
+const HIDDEN_LINE_BIAS: f32 = 0.02;
+
+/// How the raster stage draws the triangles that pass the vertex stage.
+/// ```
+/// use text_3d_graphics::prelude::*;
+/// let cube = SimpleMesh::cube_builder().size(1.0).build();
+/// let model = Mat4::from_translation(vec3(0.0, 0.0, 2.0))
+///     * Mat4::from_rotation_y(0.6)
+///     * Mat4::from_rotation_x(0.4);
+/// let drawn = |polygon_mode, cull_mode, model| {
+///     let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+///     let mut rasterizer = Rasterizer {
+///         polygon_mode,
+///         cull_mode,
+///         ..Rasterizer::default()
+///     };
+///     rasterizer.draw_mesh(&cube, model, &mut buffer).unwrap();
+///     buffer.cells().iter().filter(|c| c.char != ' ').count()
+/// };
+/// let fill = drawn(PolygonMode::Fill, CullMode::Back, model);
+/// let wireframe = drawn(PolygonMode::Wireframe, CullMode::None, model);
+/// let hidden_line = drawn(PolygonMode::HiddenLine, CullMode::None, model);
+/// let points = drawn(PolygonMode::Points, CullMode::None, model);
+/// // The wireframe includes the back edges that hidden line drawing leaves out
+/// assert!(wireframe > hidden_line);
+/// assert!(fill > points && hidden_line > points);
+/// assert!(points > 0 && points <= 8);
+/// // Edges running far off the buffer are clipped to it
+/// let wide =
+///     Mat4::from_translation(vec3(0.0, 0.0, 3.0)) * Mat4::from_scale(vec3(1e6, 1e6, 1.0));
+/// assert!(drawn(PolygonMode::Wireframe, CullMode::Back, wide) > 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PolygonMode {
+    /// Fills triangles, depth tested.
+    #[default]
+    Fill,
+    /// Draws every edge with [`Rasterizer::line_style`] without depth testing. Set
+    /// [`Rasterizer::cull_mode`] to [`CullMode::None`] to include the edges of back faces.
+    Wireframe,
+    /// Draws the edges that aren't hidden behind other triangles. The triangles only write to the
+    /// depth buffer, so the cells between edges are left as they were.
+    HiddenLine,
+    /// Plots a cell at every vertex, depth tested against each other.
+    Points,
+}
+
+/// One copy of a mesh drawn by [`Rasterizer::draw_mesh_instanced`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    /// Model matrix of this copy, applied before `world_tensor`.
+    pub transform: Mat4,
+    /// Multiplied into the mesh's colors channel by channel, white leaves them unchanged.
+    pub tint: RgbColor,
+}
+
+/// Which faces the vertex stage discards.
+/// ```
+/// use text_3d_graphics::prelude::*;
+/// let triangle = |v| SimpleMesh::from_iter([Triangle { v, ..Triangle::default() }]);
+/// let (a, b, c) = (vec3a(0.0, 0.0, 3.0), vec3a(1.0, 0.0, 3.0), vec3a(0.0, 1.0, 3.0));
+/// // World +X is to the left of a camera looking down +Z, and rows count down
+/// let (counter_clockwise, clockwise) = (triangle([a, b, c]), triangle([a, c, b]));
+/// let drawn = |mesh: &SimpleMesh, cull_mode, front_face| {
+///     let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+///     let mut rasterizer = Rasterizer {
+///         cull_mode,
+///         front_face,
+///         ..Rasterizer::default()
+///     };
+///     rasterizer.draw_mesh(mesh, Mat4::IDENTITY, &mut buffer).unwrap();
+///     buffer.cells().iter().any(|c| c.char != ' ')
+/// };
+/// for (cull_mode, front_face, expected) in [
+///     (CullMode::None, FrontFace::CounterClockwise, [true, true]),
+///     (CullMode::None, FrontFace::Clockwise, [true, true]),
+///     (CullMode::Back, FrontFace::CounterClockwise, [true, false]),
+///     (CullMode::Back, FrontFace::Clockwise, [false, true]),
+///     (CullMode::Front, FrontFace::CounterClockwise, [false, true]),
+///     (CullMode::Front, FrontFace::Clockwise, [true, false]),
+/// ] {
+///     let result = [&counter_clockwise, &clockwise].map(|m| drawn(m, cull_mode, front_face));
+///     assert_eq!(result, expected, "{cull_mode:?} {front_face:?}");
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    None,
+    #[default]
+    Back,
+    Front,
+}
+
+/// The winding order of front faces, as their projected vertices appear in the buffer with rows
+/// counting down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrontFace {
+    Clockwise,
+    #[default]
+    CounterClockwise,
+}
+
+/// A triangle after the vertex stage. `v` holds the cell coordinates in `x` and `y` and the depth
+/// in `z`, and `colors` the lit color of each vertex.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScreenTriangle {
+    pub v: [Vec3; 3],
+    pub colors: [RgbColor; 3],
+    pub id: CellId,
+    /// World space face normal, turned towards the camera.
+    pub normal: Vec3,
+    /// What per pixel shading lights each cell from.
+    pub surface: Option<Surface>,
+}
+
+/// World space positions and normals of a triangle's vertices with their unlit colors.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Surface {
+    pub positions: [Vec3; 3],
+    pub normals: [Vec3; 3],
+    pub colors: [RgbColor; 3],
+}
+
+impl Instance {
+    pub fn new(transform: Mat4) -> Self {
+        Self {
+            transform,
+            tint: RgbColor(255, 255, 255),
+        }
+    }
+    pub fn tinted(transform: Mat4, tint: RgbColor) -> Self {
+        Self { transform, tint }
+    }
+}
+
+impl From<Mat4> for Instance {
+    fn from(value: Mat4) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Rasterizer {
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &SimpleMesh,
+        model: Mat4,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        //! Draws `mesh` placed by the `model` matrix, which is applied before `world_tensor`. The
+        //! matrices are combined once for the whole mesh rather than per triangle.
+        self.draw_triangles(&mesh.triangles, Instance::new(model), 0, char_buffer)
+    }
+    pub fn draw_mesh_instanced(
+        &mut self,
+        mesh: &SimpleMesh,
+        instances: &[Instance],
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        //! Draws a copy of `mesh` for every instance, each with its own transform and tint.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! let mut rasterizer = Rasterizer::default();
+        //! let cube = SimpleMesh::cube_builder().size(0.5).build();
+        //! let instances = [-1.0, 1.0].map(|x| {
+        //!     Instance::tinted(
+        //!         Mat4::from_translation(vec3(x, 0.0, 3.0)),
+        //!         RgbColor(255, 0, 0),
+        //!     )
+        //! });
+        //! rasterizer.draw_mesh_instanced(&cube, &instances, &mut buffer).unwrap();
+        //! assert!(buffer.cells().iter().any(|c| c.char == '.'));
+        //! ```
+        instances.iter().zip(0..).try_for_each(|(instance, index)| {
+            self.draw_triangles(&mesh.triangles, *instance, index, char_buffer)
+        })
+    }
+    pub(crate) fn draw_triangles(
+        &mut self,
+        triangles: &[Triangle],
+        instance: Instance,
+        instance_index: u32,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        let dimensions = *char_buffer.dimensions();
+        if self.depth_buffer.as_ref().map(|b| b.dimensions) != Some(dimensions) {
+            self.depth_buffer = Some(DepthBuffer::new(dimensions)?);
+        }
+        if let Some(ids) = self
+            .id_buffer
+            .as_mut()
+            .filter(|b| b.dimensions() != dimensions)
+        {
+            *ids = IdBuffer::new(dimensions)?;
+        }
+        if let Some(normals) = self
+            .normal_buffer
+            .as_mut()
+            .filter(|b| b.dimensions() != dimensions)
+        {
+            *normals = NormalBuffer::new(dimensions)?;
+        }
+        self.fit_aspect_ratio(dimensions);
+        let projected = self.vertex_stage(triangles, instance, instance_index, dimensions);
+        self.raster_stage(&projected, char_buffer)
+    }
+    pub(crate) fn vertex_stage(
+        &mut self,
+        triangles: &[Triangle],
+        instance: Instance,
+        instance_index: u32,
+        dimensions: UVec2,
+    ) -> Vec<ScreenTriangle> {
+        let model = self.world_tensor * instance.transform;
+        let normal_tensor = model.inverse().transpose();
+        let mirrored = model.determinant() < 0.0;
+        // Positions go to clip space in a single multiply, world space is only needed for lighting
+        let model_view_projection = self.camera.view_projection() * model;
+        let half = screen_scale(dimensions);
+        let lighting = Lighting {
+            lights: &self.lights,
+            shadow_maps: &self.shadow_maps,
+            cel_bands: self.cel_bands,
+        };
+
+        triangles
+            .iter()
+            .zip(0..)
+            .filter_map(|(source, index)| {
+                // Mirroring models reverse the winding, the same as `Triangle::transformed`
+                let corners = if mirrored { [0, 2, 1] } else { [0, 1, 2] };
+                let mut v = [Vec3::ZERO; 3];
+                for (out, i) in v.iter_mut().zip(corners) {
+                    let clip = model_view_projection * Vec3::from(source.v[i]).extend(1.0);
+                    let ndc = clip.truncate() / clip.w;
+                    // Vertices behind the camera or closer than the near plane aren't clipped,
+                    // their triangles are skipped instead
+                    if clip.w <= 0.0 || !(0.0..=1.0).contains(&ndc.z) {
+                        return None;
+                    }
+                    *out = ((ndc.truncate() + Vec2::ONE) * half).extend(ndc.z);
+                }
+
+                // Faces are culled by the winding of their projected vertices, which unlike the
+                // angle between the normal and the look direction accounts for perspective.
+                // Rows count down, so a negative signed area winds counter-clockwise.
+                let area = (v[1] - v[0]).truncate().perp_dot((v[2] - v[0]).truncate());
+                if area == 0.0 {
+                    return None;
+                }
+                let front = match self.front_face {
+                    FrontFace::Clockwise => area > 0.0,
+                    FrontFace::CounterClockwise => area < 0.0,
+                };
+                match (self.cull_mode, front) {
+                    (CullMode::Back, false) | (CullMode::Front, true) => return None,
+                    _ => {}
+                }
+                // Lighting needs the positions and normals in world space
+                let triangle = source.transformed_with(model, normal_tensor, mirrored);
+                // Faces are lit from the side the camera sees, whichever way they are wound
+                let side = if area < 0.0 { 1.0 } else { -1.0 };
+                let face_normal = Vec3::from(triangle.normal()) * side;
+                let positions = triangle.v.map(Vec3::from);
+                let normals = match (self.shading, triangle.normals) {
+                    (ShadingMode::Flat, _) | (_, None) => [face_normal; 3],
+                    (_, Some(normals)) => normals.map(|n| Vec3::from(n) * side),
+                };
+                let base = triangle
+                    .colors
+                    .unwrap_or([triangle.color; 3])
+                    .map(|c| tint_color(c, instance.tint));
+
+                let colors = if self.shading == ShadingMode::Flat {
+                    let center = (positions[0] + positions[1] + positions[2]) / 3.0;
+                    let light = lighting.illuminate(center, face_normal);
+                    base.map(|c| lit_color(c, light))
+                } else {
+                    // Per pixel shading still lights the vertices for edges and points
+                    [0, 1, 2]
+                        .map(|i| lit_color(base[i], lighting.illuminate(positions[i], normals[i])))
+                };
+                let surface = (self.shading == ShadingMode::PerPixel).then_some(Surface {
+                    positions,
+                    normals,
+                    colors: base,
+                });
+                let id = CellId {
+                    object: self.object_id,
+                    instance: instance_index,
+                    triangle: index,
+                };
+                Some(ScreenTriangle {
+                    v,
+                    colors,
+                    id,
+                    normal: face_normal,
+                    surface,
+                })
+            })
+            .collect()
+    }
+    pub(crate) fn raster_stage(
+        &mut self,
+        triangles: &[ScreenTriangle],
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        match self.polygon_mode {
+            PolygonMode::Fill => self.fill_triangles(triangles, false, char_buffer),
+            PolygonMode::Wireframe => self.draw_edges(triangles, false, char_buffer),
+            PolygonMode::HiddenLine => {
+                self.fill_triangles(triangles, true, char_buffer)?;
+                self.draw_edges(triangles, true, char_buffer)
+            }
+            PolygonMode::Points => {
+                self.draw_points(triangles, char_buffer);
+                Ok(())
+            }
+        }
+    }
+    fn fill_triangles(
+        &mut self,
+        triangles: &[ScreenTriangle],
+        depth_only: bool,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        if let Some(tile_size) = self.tile_size {
+            return self.raster_tiles(triangles, tile_size, depth_only, char_buffer);
+        }
+        let shading = FragmentShading::new(
+            &self.lights,
+            &self.shadow_maps,
+            self.cel_bands,
+            self.fog.as_ref(),
+            self.glyph_ramp.as_deref(),
+            &self.camera,
+        );
+        let depth_buffer = self.depth_buffer.as_mut().unwrap();
+        let mut records = CellRecords {
+            ids: self.id_buffer.as_mut(),
+            normals: self.normal_buffer.as_mut(),
+        };
+        triangles.iter().try_for_each(|triangle| {
+            rasterize_screen_triangle(
+                triangle,
+                IVec2::ZERO,
+                depth_only,
+                shading,
+                char_buffer,
+                depth_buffer,
+                records.reborrow(),
+            )
+        })
+    }
+    fn draw_edges(
+        &mut self,
+        triangles: &[ScreenTriangle],
+        depth_tested: bool,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        //! Draws the edges of every triangle, colored and given depth by interpolating between
+        //! the vertices at either end. Cells an edge is drawn to record its triangle's ID.
+        let depth_buffer = self.depth_buffer.as_ref().unwrap();
+        let mut ids = self.id_buffer.as_mut();
+        let cue = DepthCue::new(self.fog.as_ref(), self.glyph_ramp.as_deref(), &self.camera);
+        // Lines reach up to half their thickness past their ends, and a cell further
+        let margin = self.line_style.thickness.max(1.0) / 2.0 + 1.0;
+        let bounds = (
+            Vec2::splat(-margin),
+            char_buffer.dimensions().as_vec2() + margin,
+        );
+        for triangle in triangles {
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                let (start, end) = (triangle.v[i], triangle.v[j]);
+                let delta = end.truncate() - start.truncate();
+                let length_squared = delta.length_squared();
+                let shader = |p: IVec2, _: &CharBuffer| -> Shade {
+                    let center = p.as_vec2() + 0.5;
+                    let t = if length_squared > 0.0 {
+                        ((center - start.truncate()).dot(delta) / length_squared).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let depth = start.z + (end.z - start.z) * t;
+                    if depth_tested {
+                        let surface = depth_buffer
+                            .get_value(p.as_uvec2())
+                            .unwrap_or(f32::INFINITY);
+                        // Depth isn't linear in distance for every projection, so the bias is
+                        // applied to the distance from the camera each depth maps back to
+                        let behind = cue.distance(surface) * (1.0 + HIDDEN_LINE_BIAS);
+                        if surface.is_finite() && cue.distance(depth) > behind {
+                            return (None, None);
+                        }
+                    }
+                    if let Some(ids) = ids.as_deref_mut() {
+                        ids.set_value(p.as_uvec2(), Some(triangle.id));
+                    }
+                    cue.shade(mix_colors(triangle.colors[i], triangle.colors[j], t), depth)
+                };
+                // Depth and color are still interpolated along the whole edge
+                let Some((from, to)) = clip_segment(start.truncate(), end.truncate(), bounds)
+                else {
+                    continue;
+                };
+                char_buffer
+                    .draw_line_styled(from, to, &self.line_style, shader)
+                    .change_context(RasterizationError::LineRasterization)?;
+            }
+        }
+        Ok(())
+    }
+    fn draw_points(&mut self, triangles: &[ScreenTriangle], char_buffer: &mut CharBuffer) {
+        let cue = DepthCue::new(self.fog.as_ref(), self.glyph_ramp.as_deref(), &self.camera);
+        let depth_buffer = self.depth_buffer.as_mut().unwrap();
+        let vertices = triangles
+            .iter()
+            .flat_map(|t| t.v.into_iter().zip(t.colors).map(move |(v, c)| (v, c, t)));
+        for (v, color, triangle) in vertices {
+            let cell = v.truncate().floor().as_ivec2();
+            if !char_buffer.is_valid_point(cell) {
+                continue;
+            }
+            let cell = cell.as_uvec2();
+            if depth_buffer
+                .get_value(cell)
+                .is_some_and(|depth| v.z > depth)
+            {
+                continue;
+            }
+            depth_buffer.set_value(cell, v.z);
+            if let Some(ids) = self.id_buffer.as_mut() {
+                ids.set_value(cell, Some(triangle.id));
+            }
+            if let Some(normals) = self.normal_buffer.as_mut() {
+                // SAFETY: the normal buffer has the same dimensions as the char buffer
+                unsafe { *normals.get_unchecked_mut(cell) = triangle.normal };
+            }
+            let (char, color) = cue.shade(color, v.z);
+            let _ = char_buffer.set_char(cell, char, color);
+        }
+    }
+}
+
+/// How every fragment of a draw is lit and cued by depth.
+#[derive(Clone, Copy)]
+pub(crate) struct FragmentShading<'a> {
+    pub lighting: Lighting<'a>,
+    pub depth_cue: DepthCue<'a>,
+}
+
+/// The per cell buffers recorded alongside depth, each only when enabled.
+pub(crate) struct CellRecords<'a> {
+    pub ids: Option<&'a mut IdBuffer>,
+    pub normals: Option<&'a mut NormalBuffer>,
+}
+
+impl CellRecords<'_> {
+    pub(crate) fn reborrow(&mut self) -> CellRecords<'_> {
+        CellRecords {
+            ids: self.ids.as_deref_mut(),
+            normals: self.normals.as_deref_mut(),
+        }
+    }
+}
+
+impl<'a> FragmentShading<'a> {
+    pub(crate) fn new(
+        lights: &'a [Light],
+        shadow_maps: &'a [Option<ShadowMap>],
+        cel_bands: Option<u32>,
+        fog: Option<&'a Fog>,
+        glyph_ramp: Option<&'a [char]>,
+        camera: &Camera,
+    ) -> Self {
+        Self {
+            lighting: Lighting {
+                lights,
+                shadow_maps,
+                cel_bands,
+            },
+            depth_cue: DepthCue::new(fog, glyph_ramp, camera),
+        }
+    }
+}
+
+pub(crate) fn rasterize_screen_triangle(
+    triangle: &ScreenTriangle,
+    offset: IVec2,
+    depth_only: bool,
+    shading: FragmentShading,
+    char_buffer: &mut CharBuffer,
+    depth_buffer: &mut DepthBuffer,
+    mut records: CellRecords,
+) -> Result<(), RasterizationError> {
+    //! Fills the cells `triangle` covers, or only their depth, ID and normal when `depth_only`. The
+    //! buffers may be a region of the full frame starting at `offset`, in which case `triangle`
+    //! is still given in full frame coordinates.
+    let pv = triangle.v;
+    let flat = triangle.colors[0] == triangle.colors[1] && triangle.colors[1] == triangle.colors[2];
+
+    // get fn for point on screen -> calculated z value based on projected coords
+    let n = (pv[2] - pv[0]).cross(pv[1] - pv[0]);
+    let depth =
+        |p: Vec2| -> f32 { pv[0].z - (n.x * (p.x - pv[0].x) + n.y * (p.y - pv[0].y)) / n.z };
+
+    // create shader including depth buffer check using closure defined above
+    assert_eq!(*char_buffer.dimensions(), depth_buffer.dimensions());
+    if let Some(ids) = records.ids.as_ref() {
+        assert_eq!(ids.dimensions(), depth_buffer.dimensions());
+    }
+    if let Some(normals) = records.normals.as_ref() {
+        assert_eq!(normals.dimensions(), depth_buffer.dimensions());
+    }
+    let shader = |local: IVec2, _: &CharBuffer| -> Shade {
+        // Cells are sampled at their centers
+        let p = (local + offset).as_vec2() + 0.5;
+        let current_depth = depth(p);
+        // SAFETY: draw_triangle only shades points inside the char buffer, which has the same
+        // dimensions as the depth buffer
+        let old_depth = unsafe { depth_buffer.get_unchecked_mut(local.as_uvec2()) };
+        if current_depth > *old_depth {
+            return (None, None);
+        }
+        *old_depth = current_depth;
+        // SAFETY: the ID and normal buffers have the same dimensions as the depth buffer
+        if let Some(ids) = records.ids.as_deref_mut() {
+            unsafe { *ids.get_unchecked_mut(local.as_uvec2()) = Some(triangle.id) };
+        }
+        if let Some(normals) = records.normals.as_deref_mut() {
+            unsafe { *normals.get_unchecked_mut(local.as_uvec2()) = triangle.normal };
+        }
+        if depth_only {
+            return (None, None);
+        }
+        // Vertex attributes are interpolated in screen space
+        let color = if let Some(surface) = triangle.surface {
+            let w = barycentric(pv.map(|v| v.truncate()), p);
+            let normal = interpolate(surface.normals, w).normalize_or_zero();
+            let light = shading
+                .lighting
+                .illuminate(interpolate(surface.positions, w), normal);
+            lit_color(interpolate_colors(surface.colors, w), light)
+        } else if flat {
+            triangle.colors[0]
+        } else {
+            let w = barycentric(pv.map(|v| v.truncate()), p);
+            interpolate_colors(triangle.colors, w)
+        };
+        shading.depth_cue.shade(color, current_depth)
+    };
+
+    // Vertices are snapped to the subpixel grid before moving them into the region so that every
+    // region rounds them the same way
+    let offset = offset.as_i64vec2() << SUBPIXEL_BITS;
+    char_buffer.draw_triangle_fixed(pv.map(|v| to_fixed(v.truncate()) - offset), shader);
+    Ok(())
+}
+
+/// Barycentric weights of `p` within the triangle `v`. Weights are clamped to be non-negative so
+/// that cells rasterized just past an edge don't extrapolate.
+fn barycentric(v: [Vec2; 3], p: Vec2) -> Vec3 {
+    let area = (v[1] - v[0]).perp_dot(v[2] - v[0]);
+    if area.abs() <= f32::EPSILON {
+        return vec3(1.0, 0.0, 0.0);
+    }
+    let w = vec3(
+        (v[2] - v[1]).perp_dot(p - v[1]),
+        (v[0] - v[2]).perp_dot(p - v[2]),
+        (v[1] - v[0]).perp_dot(p - v[0]),
+    ) / area;
+    let w = w.max(Vec3::ZERO);
+    w / (w.x + w.y + w.z).max(f32::EPSILON)
+}
+
+/// The part of the segment from `start` to `end` within the rectangle `bounds`, if any.
+fn clip_segment(start: Vec2, end: Vec2, (min, max): (Vec2, Vec2)) -> Option<(Vec2, Vec2)> {
+    let delta = end - start;
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for axis in 0..2 {
+        if delta[axis] == 0.0 {
+            if start[axis] < min[axis] || start[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - start[axis]) / delta[axis];
+        let b = (max[axis] - start[axis]) / delta[axis];
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+    (enter <= exit).then(|| (start + delta * enter, start + delta * exit))
+}
+
+fn interpolate(values: [Vec3; 3], w: Vec3) -> Vec3 {
+    values[0] * w.x + values[1] * w.y + values[2] * w.z
+}
+
+fn interpolate_colors(colors: [RgbColor; 3], w: Vec3) -> RgbColor {
+    let c = colors.map(|c| vec3(c.0 as f32, c.1 as f32, c.2 as f32));
+    // Rounded, as weights summing to a little under 1 would otherwise darken equal colors
+    let c = (c[0] * w.x + c[1] * w.y + c[2] * w.z).round();
+    RgbColor(c.x as u8, c.y as u8, c.z as u8)
+}
+
+fn tint_color(color: RgbColor, tint: RgbColor) -> RgbColor {
+    let channel = |c: u8, t: u8| ((c as u16 * t as u16) / 255) as u8;
+    RgbColor(
+        channel(color.0, tint.0),
+        channel(color.1, tint.1),
+        channel(color.2, tint.2),
+    )
+}
@@ -0,0 +1,216 @@
+//! Toon outlines, drawn over a finished frame. Silhouette edges are found where the depth jumps
+//! between neighbouring cells, and crease edges where the surface normals recorded in a
+//! [`NormalBuffer`] turn sharply. Pair with [`Rasterizer::cel_bands`] for a comic look.
+//! ```
+//! use std::collections::HashSet;
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::rasterizing::edges::{NormalBuffer, OutlineStyle};
+//! let dimensions = uvec2(40, 20);
+//! let rasterizer = Rasterizer {
+//!     camera: Camera::builder()
+//!         .position(Vec3::ZERO)
+//!         .fov_y_radians(1.0)
+//!         .aspect_ratio(2.0)
+//!         .build(),
+//!     ..Rasterizer::default()
+//! };
+//! let cube = SimpleMesh::cube_builder().size(1.5).build();
+//! let model = Mat4::from_translation(vec3(0.0, 0.0, 4.0))
+//!     * Mat4::from_rotation_x(0.5)
+//!     * Mat4::from_rotation_y(0.7);
+//! // Above the change in depth between the cells of the cube's steepest face
+//! let style = OutlineStyle::builder().depth_threshold(0.3).build();
+//! let cells = |buffer: &CharBuffer, drawn: fn(char) -> bool| {
+//!     let drawn = buffer.positions().filter(|(_, c)| drawn(c.char));
+//!     drawn.map(|(p, _)| p.as_ivec2()).collect::<HashSet<_>>()
+//! };
+//! let outline = |normal_buffer: Option<NormalBuffer>| {
+//!     let mut buffer = CharBuffer::new(dimensions, ' ', RgbColor(0, 0, 0)).unwrap();
+//!     let mut rasterizer = Rasterizer {
+//!         normal_buffer,
+//!         ..rasterizer.clone()
+//!     };
+//!     rasterizer.draw_mesh(&cube, model, &mut buffer).unwrap();
+//!     let covered = cells(&buffer, |c| c != ' ');
+//!     rasterizer.draw_outlines(&mut buffer, &style);
+//!     (covered, cells(&buffer, |c| c != ' ' && c != '.'))
+//! };
+//!
+//! // From depth alone, exactly the cube's cells next to the background are outlined
+//! let (covered, outlined) = outline(None);
+//! let sides = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+//! let silhouette = covered
+//!     .iter()
+//!     .copied()
+//!     .filter(|p| sides.iter().any(|side| !covered.contains(&(*p + *side))))
+//!     .collect::<HashSet<_>>();
+//! assert!(!silhouette.is_empty());
+//! assert_eq!(outlined, silhouette);
+//!
+//! // Normals add the creases between the cube's faces, inside the silhouette
+//! let (_, creased) = outline(Some(NormalBuffer::new(dimensions).unwrap()));
+//! assert!(creased.len() > silhouette.len());
+//! assert!(creased.is_superset(&silhouette) && creased.is_subset(&covered));
+//!
+//! // Cel shading rounds the light up to a few bands of brightness
+//! let sphere = SimpleMesh::uv_sphere_builder().segments(32).rings(16).build();
+//! let brightness_levels = |cel_bands| {
+//!     let mut buffer = CharBuffer::new(dimensions, ' ', RgbColor(0, 0, 0)).unwrap();
+//!     let mut rasterizer = Rasterizer {
+//!         shading: ShadingMode::PerPixel,
+//!         cel_bands,
+//!         ..rasterizer.clone()
+//!     };
+//!     let model = Mat4::from_translation(vec3(0.0, 0.0, 4.0));
+//!     rasterizer.draw_mesh(&sphere, model, &mut buffer).unwrap();
+//!     let drawn = buffer.cells().iter().filter(|c| c.char != ' ');
+//!     drawn.map(|c| (c.fg.0, c.fg.1, c.fg.2)).collect::<HashSet<_>>().len()
+//! };
+//! assert!(brightness_levels(None) > 3);
+//! assert!(brightness_levels(Some(3)) <= 3);
+//! ```
+pub(crate) use super::*;
+use fog::DepthCue;
+
+/// The world space normal of the closest fragment drawn to each cell. Cells nothing was drawn to
+/// hold [`Vec3::ZERO`].
+pub type NormalBuffer = CellGrid<Vec3>;
+
+/// Which edges [`Rasterizer::draw_outlines`] finds and how it draws them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineStyle {
+    /// How much further from the camera, as a fraction of its own distance, a neighbouring cell
+    /// must be for a cell to lie on a silhouette.
+    pub depth_threshold: f32,
+    /// Smallest angle in radians between the normals of neighbouring cells that makes a crease.
+    /// Creases are only found with a [`Rasterizer::normal_buffer`].
+    pub crease_angle: f32,
+    /// Drawn on every edge cell, `None` picks `|`, `-`, `/`, `\` or `+` following the edge.
+    pub glyph: Option<char>,
+    /// `None` keeps the color of the cells the outline is drawn over.
+    pub color: Option<RgbColor>,
+}
+
+impl NormalBuffer {
+    pub fn new(dimensions: UVec2) -> Result<Self, RasterizationError> {
+        Self::filled(dimensions, Vec3::ZERO)
+    }
+    pub fn clear(&mut self) {
+        self.fill(Vec3::ZERO);
+    }
+}
+
+#[buildstructor]
+impl OutlineStyle {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        depth_threshold: Option<f32>,
+        crease_angle: Option<f32>,
+        glyph: Option<char>,
+        color: Option<RgbColor>,
+    ) -> Self {
+        Self {
+            depth_threshold: depth_threshold.unwrap_or(0.1),
+            crease_angle: crease_angle.unwrap_or(1.0),
+            glyph,
+            color,
+        }
+    }
+}
+
+impl std::default::Default for OutlineStyle {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Rasterizer {
+    pub fn draw_outlines(&self, char_buffer: &mut CharBuffer, style: &OutlineStyle) {
+        //! Draws outlines over the frame last drawn to `char_buffer`, from `depth_buffer` and
+        //! `normal_buffer` if it is set. Outlines are drawn on the cells of the nearer surface
+        //! at every edge.
+        let Some(depths) = self.depth_buffer.as_ref() else {
+            return;
+        };
+        let normals = self
+            .normal_buffer
+            .as_ref()
+            .filter(|normals| normals.dimensions() == depths.dimensions());
+        let cue = DepthCue::new(None, None, &self.camera);
+        let distances: Vec<f32> = depths
+            .values()
+            .iter()
+            .map(|&depth| {
+                if depth.is_finite() {
+                    cue.distance(depth)
+                } else {
+                    depth
+                }
+            })
+            .collect();
+        let dimensions = depths.dimensions().as_ivec2();
+        let distance = |p: IVec2| distances[(p.y * dimensions.x + p.x) as usize];
+        let cos_crease = style.crease_angle.cos();
+
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                let p = ivec2(x, y);
+                let d = distance(p);
+                if !d.is_finite() {
+                    continue;
+                }
+                // At most one edge per neighbour, kept on the stack as this runs for every cell
+                let mut edges = [IVec2::ZERO; 4];
+                let mut edge_count = 0;
+                for dir in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                    let q = p + dir;
+                    // The frame's border isn't an edge
+                    if q.cmplt(IVec2::ZERO).any() || q.cmpge(dimensions).any() {
+                        continue;
+                    }
+                    let silhouette = distance(q) > d * (1.0 + style.depth_threshold);
+                    // Creases are drawn on one side only, keeping them a cell thick
+                    let crease = || {
+                        let normal = |p: IVec2| normals.unwrap().get_value(p.as_uvec2()).unwrap();
+                        let (a, b) = (normal(p), normal(q));
+                        a != Vec3::ZERO && b != Vec3::ZERO && a.dot(b) < cos_crease
+                    };
+                    // Cells behind another surface's silhouette have no crease with it
+                    let occluded = d > distance(q) * (1.0 + style.depth_threshold);
+                    let forward = dir == IVec2::X || dir == IVec2::Y;
+                    if silhouette || (normals.is_some() && forward && !occluded && crease()) {
+                        edges[edge_count] = dir;
+                        edge_count += 1;
+                    }
+                }
+                if edge_count == 0 {
+                    continue;
+                }
+                let glyph = style
+                    .glyph
+                    .unwrap_or_else(|| edge_glyph(&edges[..edge_count]));
+                let _ = char_buffer.set_char(p.as_uvec2(), Some(glyph), style.color);
+            }
+        }
+    }
+}
+
+fn edge_glyph(edges: &[IVec2]) -> char {
+    //! A glyph running along an edge, from the directions of the neighbours across it. Rows
+    //! count down, so neighbours across both axes with the same sign lie across a `/`.
+    let sum: IVec2 = edges.iter().sum();
+    match (sum.x.signum(), sum.y.signum()) {
+        (0, 0) => match (
+            edges.iter().any(|e| e.x != 0),
+            edges.iter().any(|e| e.y != 0),
+        ) {
+            (true, false) => '|',
+            (false, true) => '-',
+            _ => '+',
+        },
+        (_, 0) => '|',
+        (0, _) => '-',
+        (x, y) if x == y => '/',
+        _ => '\\',
+    }
+}
@@ -0,0 +1,127 @@
+//! Depth cueing, so distance reads at terminal resolution. [`Fog`] blends fragments toward its
+//! color the further they are from the camera, and with a [`Rasterizer::glyph_ramp`] fragments
+//! are drawn with lighter glyphs as they fade.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::rasterizing::fog::GLYPH_RAMP;
+//! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+//! let mut rasterizer = Rasterizer {
+//!     fog: Some(
+//!         Fog::builder()
+//!             .falloff(FogFalloff::Linear { start: 2.0, end: 6.0 })
+//!             .color(RgbColor(0, 0, 255))
+//!             .build(),
+//!     ),
+//!     glyph_ramp: Some(GLYPH_RAMP.chars().collect()),
+//!     ..Rasterizer::default()
+//! };
+//! let cube = SimpleMesh::cube_builder().size(1.0).build();
+//! rasterizer
+//!     .draw_mesh(&cube, Mat4::from_translation(vec3(0.0, 0.0, 4.0)), &mut buffer)
+//!     .unwrap();
+//! let cell = buffer.cells()[10 * 40 + 20];
+//! assert!(cell.fg.2 > cell.fg.0);
+//! assert!(GLYPH_RAMP.contains(cell.char));
+//! ```
+pub(crate) use super::*;
+use crate::printing::lines::mix_colors;
+
+/// Glyphs from lightest to heaviest, for [`Rasterizer::glyph_ramp`].
+pub const GLYPH_RAMP: &str = ".:-=+*#%@";
+
+/// How much fog lies between the camera and a fragment, by its view space depth `d`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogFalloff {
+    /// None before `start`, thickening evenly until fragments past `end` are hidden entirely.
+    Linear { start: f32, end: f32 },
+    /// `1 - e^(-density * d)`.
+    Exponential { density: f32 },
+    /// `1 - e^(-(density * d)²)`, clearer up close and thicker further away than exponential
+    /// fog of the same density.
+    ExponentialSquared { density: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub falloff: FogFalloff,
+    /// What fragments fade to, usually the background.
+    pub color: RgbColor,
+}
+
+/// What depth cueing needs while shading fragments.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DepthCue<'a> {
+    fog: Option<&'a Fog>,
+    glyph_ramp: Option<&'a [char]>,
+    inverse_projection: Mat4,
+}
+
+#[buildstructor]
+impl Fog {
+    #[builder(visibility = "pub")]
+    pub fn new(falloff: FogFalloff, color: Option<RgbColor>) -> Self {
+        Self {
+            falloff,
+            color: color.unwrap_or(RgbColor(0, 0, 0)),
+        }
+    }
+}
+
+impl Fog {
+    pub fn amount(&self, depth: f32) -> f32 {
+        //! How much a fragment `depth` in front of the camera fades, from 0 untouched to 1
+        //! entirely fog.
+        let amount = match self.falloff {
+            FogFalloff::Linear { start, end } if end > start => (depth - start) / (end - start),
+            FogFalloff::Linear { start, .. } => (depth >= start) as u8 as f32,
+            FogFalloff::Exponential { density } => 1.0 - (-density * depth.max(0.0)).exp(),
+            FogFalloff::ExponentialSquared { density } => {
+                1.0 - (-(density * depth.max(0.0)).powi(2)).exp()
+            }
+        };
+        amount.clamp(0.0, 1.0)
+    }
+}
+
+impl<'a> DepthCue<'a> {
+    pub(crate) fn new(
+        fog: Option<&'a Fog>,
+        glyph_ramp: Option<&'a [char]>,
+        camera: &Camera,
+    ) -> Self {
+        Self {
+            fog,
+            glyph_ramp,
+            inverse_projection: camera.projection_matrix().inverse(),
+        }
+    }
+    pub(crate) fn distance(&self, depth: f32) -> f32 {
+        //! View space depth of a fragment from its depth in normalized device coordinates.
+        let p = self.inverse_projection * vec4(0.0, 0.0, depth, 1.0);
+        -p.z / p.w
+    }
+    pub(crate) fn shade(&self, color: RgbColor, depth: f32) -> Shade {
+        //! The glyph and color of a lit fragment `depth` away in normalized device coordinates.
+        if self.fog.is_none() && self.glyph_ramp.is_none() {
+            return (Some('.'), Some(color));
+        }
+        let fog = self.fog.map_or(0.0, |fog| fog.amount(self.distance(depth)));
+        let glyph = match self.glyph_ramp {
+            Some(ramp) if !ramp.is_empty() => {
+                let level = luminance(color) * (1.0 - fog);
+                ramp[(level * (ramp.len() - 1) as f32).round() as usize]
+            }
+            _ => '.',
+        };
+        let color = match self.fog {
+            Some(f) => mix_colors(color, f.color, fog),
+            None => color,
+        };
+        (Some(glyph), Some(color))
+    }
+}
+
+/// Perceived brightness of `color` from 0 to 1.
+fn luminance(color: RgbColor) -> f32 {
+    (0.2126 * color.0 as f32 + 0.7152 * color.1 as f32 + 0.0722 * color.2 as f32) / 255.0
+}
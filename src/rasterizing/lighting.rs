@@ -0,0 +1,256 @@
+//! Lights and how surfaces are lit by them. Every light adds its color, scaled by its intensity
+//! and how directly it reaches the surface, to the light falling on a surface, which then
+//! multiplies the surface's own color.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+//! let mut rasterizer = Rasterizer {
+//!     lights: vec![
+//!         Light::builder().kind(LightKind::Ambient).intensity(0.2).build(),
+//!         Light::builder()
+//!             .kind(LightKind::Point {
+//!                 position: vec3(0.0, 0.0, 1.0),
+//!                 attenuation: Attenuation::default(),
+//!             })
+//!             .color(RgbColor(255, 128, 0))
+//!             .build(),
+//!     ],
+//!     shading: ShadingMode::PerPixel,
+//!     ..Rasterizer::default()
+//! };
+//! let cube = SimpleMesh::cube_builder().size(1.0).build();
+//! rasterizer
+//!     .draw_mesh(&cube, Mat4::from_translation(vec3(0.0, 0.0, 3.0)), &mut buffer)
+//!     .unwrap();
+//! assert!(buffer.cells().iter().any(|c| c.char == '.' && c.fg.0 > c.fg.2));
+//! ```
+pub(crate) use super::*;
+use shadows::ShadowMap;
+
+/// Where a light is and which way it shines. Positions and directions are in world space for
+/// [`Rasterizer::lights`], and relative to the node for lights in a [`Scene`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Light reaching every surface evenly, regardless of orientation.
+    Ambient,
+    /// Parallel light travelling along `direction`, like sunlight.
+    Directional { direction: Vec3 },
+    /// Light shining out in every direction from `position`.
+    Point {
+        position: Vec3,
+        attenuation: Attenuation,
+    },
+    /// Light shining from `position` in a cone around `direction`. Surfaces within
+    /// `inner_angle` of the direction get the full light, fading out towards `outer_angle`.
+    /// Angles are in radians from the center of the cone.
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        attenuation: Attenuation,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: RgbColor,
+    pub intensity: f32,
+    /// Whether meshes block this light from the surfaces behind them. Only directional and spot
+    /// lights cast shadows, see [`shadows`].
+    pub casts_shadows: bool,
+}
+
+/// How the light of point and spot lights falls off with distance `d`, as
+/// `1 / (constant + linear * d + quadratic * d²)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+/// Where lighting is worked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingMode {
+    /// Once per triangle from its face normal at its center.
+    #[default]
+    Flat,
+    /// At every vertex from its normal, with the colors interpolated across the triangle.
+    PerVertex,
+    /// At every cell from the position and normal interpolated across the triangle.
+    PerPixel,
+}
+
+#[buildstructor]
+impl Light {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        kind: LightKind,
+        color: Option<RgbColor>,
+        intensity: Option<f32>,
+        casts_shadows: Option<bool>,
+    ) -> Self {
+        Self {
+            kind,
+            color: color.unwrap_or(RgbColor(255, 255, 255)),
+            intensity: intensity.unwrap_or(1.0),
+            casts_shadows: casts_shadows.unwrap_or(false),
+        }
+    }
+}
+
+impl Light {
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        //! The light moved by `transform`.
+        let kind = match self.kind {
+            LightKind::Ambient => LightKind::Ambient,
+            LightKind::Directional { direction } => LightKind::Directional {
+                direction: transform.transform_vector3(direction).normalize_or_zero(),
+            },
+            LightKind::Point {
+                position,
+                attenuation,
+            } => LightKind::Point {
+                position: transform.transform_point3(position),
+                attenuation,
+            },
+            LightKind::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                attenuation,
+            } => LightKind::Spot {
+                position: transform.transform_point3(position),
+                direction: transform.transform_vector3(direction).normalize_or_zero(),
+                inner_angle,
+                outer_angle,
+                attenuation,
+            },
+        };
+        Self { kind, ..*self }
+    }
+    pub fn illuminance(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        //! The light this light casts on a surface at `position` facing `normal`, per color
+        //! channel, where 1 leaves the surface's color as it is.
+        let amount = match self.kind {
+            LightKind::Ambient => 1.0,
+            LightKind::Directional { direction } => {
+                normal.dot(-direction.normalize_or_zero()).max(0.0)
+            }
+            LightKind::Point {
+                position: light,
+                attenuation,
+            } => {
+                let (to_light, distance) = towards(position, light);
+                normal.dot(to_light).max(0.0) * attenuation.at(distance)
+            }
+            LightKind::Spot {
+                position: light,
+                direction,
+                inner_angle,
+                outer_angle,
+                attenuation,
+            } => {
+                let (to_light, distance) = towards(position, light);
+                let cos = (-to_light).dot(direction.normalize_or_zero());
+                let (cos_inner, cos_outer) = (inner_angle.cos(), outer_angle.cos());
+                let cone = if cos_inner > cos_outer {
+                    ((cos - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0)
+                } else if cos >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                normal.dot(to_light).max(0.0) * attenuation.at(distance) * cone
+            }
+        };
+        let color = vec3(
+            self.color.0 as f32,
+            self.color.1 as f32,
+            self.color.2 as f32,
+        ) / 255.0;
+        color * (self.intensity * amount)
+    }
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Self {
+        Self {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+    pub fn at(&self, distance: f32) -> f32 {
+        //! The fraction of the light left `distance` away.
+        1.0 / (self.constant + self.linear * distance + self.quadratic * distance * distance)
+            .max(f32::EPSILON)
+    }
+}
+
+impl std::default::Default for Attenuation {
+    fn default() -> Self {
+        Self::new(1.0, 0.0, 1.0)
+    }
+}
+
+/// Unit direction from `from` to `to` and the distance between them.
+fn towards(from: Vec3, to: Vec3) -> (Vec3, f32) {
+    let delta = to - from;
+    let distance = delta.length();
+    (delta / distance.max(f32::EPSILON), distance)
+}
+
+/// The lights a frame is lit by, along with the shadow map of each light that has one.
+#[derive(Clone, Copy)]
+pub(crate) struct Lighting<'a> {
+    pub lights: &'a [Light],
+    /// Index aligned with `lights`. Missing maps, and maps made for a different light than the
+    /// one at their index, leave their light unshadowed.
+    pub shadow_maps: &'a [Option<ShadowMap>],
+    pub cel_bands: Option<u32>,
+}
+
+impl Lighting<'_> {
+    pub(crate) fn illuminate(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        //! The light all `lights` cast together on a surface, less what shadows block, in
+        //! `cel_bands` if set.
+        let light: Vec3 = self
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| {
+                let received = light.illuminance(position, normal);
+                match self.shadow_maps.get(i) {
+                    Some(Some(map)) if received != Vec3::ZERO && map.light() == light => {
+                        received * map.visibility(position)
+                    }
+                    _ => received,
+                }
+            })
+            .sum();
+        match self.cel_bands {
+            Some(bands) if bands > 0 && light.max_element() > 0.0 => {
+                // Scaling the whole light keeps its hue while its brightness snaps to a band
+                let level = light.max_element();
+                let banded = (level * bands as f32).ceil() / bands as f32;
+                light * (banded / level)
+            }
+            _ => light,
+        }
+    }
+}
+
+pub(crate) fn lit_color(color: RgbColor, light: Vec3) -> RgbColor {
+    // Rounded, so that lights a rounding error apart, like those snapped to the same cel band,
+    // give the same color
+    let channel = |c: u8, l: f32| (c as f32 * l).round().min(255.0) as u8;
+    RgbColor(
+        channel(color.0, light.x),
+        channel(color.1, light.y),
+        channel(color.2, light.z),
+    )
+}
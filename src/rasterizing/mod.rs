@@ -1,5 +1,19 @@
 pub(crate) use super::*;
 use crate::prelude::*;
+use crate::printing::drawing::Shade;
+use crate::printing::lines::LineStyle;
+use crate::printing::{terminal_cell_aspect, DEFAULT_CELL_ASPECT};
+use crate::resources::processing::Aabb;
+use crate::resources::raycast::Ray;
+
+pub mod controllers;
+pub mod draw;
+pub mod edges;
+pub mod fog;
+pub mod lighting;
+pub mod picking;
+pub mod shadows;
+pub mod tiles;
 
 #[derive(Debug, Error)]
 pub enum RasterizationError {
@@ -7,29 +21,93 @@ pub enum RasterizationError {
     U32ToUsize,
     #[error("An error occured while rasterizing a triangle")]
     TriangleRasterization,
+    #[error("An error occured while drawing the edges of a triangle")]
+    LineRasterization,
     #[error("unidentified rasterization error")]
     Misc,
 }
 
+/// A value for every cell of a frame, stored row by row.
 #[derive(Debug, Clone)]
-pub struct DepthBuffer {
-    data: Vec<Vec<f32>>,
+pub struct CellGrid<T> {
+    data: Vec<T>,
     dimensions: UVec2,
 }
 
+/// Depth of the closest fragment drawn to each cell.
+pub type DepthBuffer = CellGrid<f32>;
+
 #[derive(Debug, Clone)]
 pub struct Rasterizer {
     pub camera: Camera,
     pub world_tensor: Mat4,
-    pub light_dir: Vec3,
-    pub universal_lighting: f32,
+    /// Lights in world space, after `world_tensor`.
+    pub lights: Vec<lighting::Light>,
+    pub shading: lighting::ShadingMode,
+    /// Rounds lighting up to this many evenly spaced bands of brightness for a cel shaded look.
+    pub cel_bands: Option<u32>,
+    /// Fades fragments with their distance from the camera when set.
+    pub fog: Option<fog::Fog>,
+    /// Glyphs from lightest to heaviest that fragments are drawn with by their brightness,
+    /// lighter the more fog they are in. `None` draws every fragment as `.`.
+    pub glyph_ramp: Option<Vec<char>>,
+    pub shadow_settings: shadows::ShadowSettings,
+    /// Shadow map of each light in `lights` that casts shadows, at the same index as its light,
+    /// made by [`Rasterizer::begin_shadows`]. Make them again after changing `lights`, maps whose
+    /// light no longer matches are ignored.
+    pub shadow_maps: Vec<Option<shadows::ShadowMap>>,
     pub depth_buffer: Option<DepthBuffer>,
+    /// Records what was drawn to each cell when set, resized along with the depth buffer.
+    pub id_buffer: Option<picking::IdBuffer>,
+    /// Records the normal of what was drawn to each cell when set, for crease edges in
+    /// [`Rasterizer::draw_outlines`]. Resized along with the depth buffer.
+    pub normal_buffer: Option<edges::NormalBuffer>,
+    /// Object ID recorded in the ID buffer by the following draws. [`Rasterizer::render_scene`]
+    /// sets it to the index of each node it draws.
+    pub object_id: u32,
+    /// How many front facing triangles per covered cell [`Rasterizer::select_lod`] aims for.
+    pub lod_triangles_per_cell: f32,
+    /// Size of the tiles the frame is split into to rasterize in parallel, `None` rasterizes on
+    /// the calling thread. Both produce the same frame.
+    pub tile_size: Option<UVec2>,
+    /// Which faces are discarded before rasterizing, judged by `front_face`.
+    pub cull_mode: draw::CullMode,
+    pub front_face: draw::FrontFace,
+    /// Whether triangles are filled or drawn as edges or points.
+    pub polygon_mode: draw::PolygonMode,
+    /// Style of the edges drawn in the wireframe and hidden-line polygon modes.
+    pub line_style: LineStyle,
+    /// Width over height of a buffer cell as printed. When set, the camera's aspect ratio is
+    /// derived from it and the buffer's dimensions on every draw, replacing the one it was given,
+    /// so shapes keep their proportions in any font. [`DEFAULT_CELL_ASPECT`] by default, see
+    /// [`Rasterizer::detect_cell_aspect`] to use the terminal's own. `None` leaves the camera's
+    /// aspect ratio alone.
+    pub cell_aspect: Option<f32>,
+}
+
+/// How a [`Camera`] maps view space to normalized device coordinates. Every projection puts the
+/// near plane at depth 0 and the far plane, if it has one, at depth 1. Anything outside that range
+/// is not drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    /// Perspective from `fov_y_radians` and `aspect_ratio` with nothing drawn past `z_far`.
+    Perspective { z_far: f32 },
+    /// Perspective from `fov_y_radians` and `aspect_ratio` without a far plane.
+    #[default]
+    InfinitePerspective,
+    /// Parallel projection of a box `height` units tall and `height * aspect_ratio` wide, from
+    /// `z_near` to `z_far`.
+    Orthographic { height: f32, z_far: f32 },
+    /// A projection matrix following the same conventions, `fov_y_radians`, `aspect_ratio` and
+    /// `z_near` are ignored.
+    Custom(Mat4),
 }
 
 #[derive(Debug, Clone)]
 pub struct Camera {
     view_tensor: Option<Mat4>,
-    perspective_tesnor: Option<Mat4>,
+    projection_tensor: Option<Mat4>,
+    projection: Projection,
     up_dir: Vec3,
     look_dir: Vec3,
     position: Vec3,
@@ -38,175 +116,349 @@ pub struct Camera {
     z_near: f32,
 }
 
-impl DepthBuffer {
-    pub fn new(dimensions: UVec2) -> Result<Self, RasterizationError> {
+impl<T: Copy> CellGrid<T> {
+    pub fn filled(dimensions: UVec2, value: T) -> Result<Self, RasterizationError> {
+        let len = TryInto::<usize>::try_into(dimensions.x)
+            .ok()
+            .zip(TryInto::<usize>::try_into(dimensions.y).ok())
+            .and_then(|(x, y)| x.checked_mul(y))
+            .ok_or_else(|| Report::new(RasterizationError::U32ToUsize))?;
         Ok(Self {
             dimensions,
-            data: vec![
-                vec![
-                    f32::INFINITY;
-                    TryInto::<usize>::try_into(dimensions.x)
-                        .change_context_lazy(|| RasterizationError::U32ToUsize)?
-                ];
-                TryInto::<usize>::try_into(dimensions.y)
-                    .change_context_lazy(|| RasterizationError::U32ToUsize)?
-            ],
+            data: vec![value; len],
         })
     }
-    pub fn clear(&mut self) {
-        self.data
-            .iter_mut()
-            .for_each(|v| v.iter_mut().for_each(|p| *p = f32::INFINITY));
-    }
-    pub fn get_value(&self, position: UVec2) -> Option<f32> {
-        self.data
-            .get(
-                TryInto::<usize>::try_into(position.y)
-                    .change_context_lazy(|| RasterizationError::U32ToUsize)
-                    .unwrap_or_else(|e| panic!("{}", e)),
-            )
-            .map(|v: &Vec<f32>| {
-                v.get(
-                    TryInto::<usize>::try_into(position.x)
-                        .change_context_lazy(|| RasterizationError::U32ToUsize)
-                        .unwrap_or_else(|e| panic!("{}", e)),
-                )
-            })
-            .flatten()
-            .copied()
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+    #[inline]
+    fn index(&self, position: UVec2) -> Option<usize> {
+        (position.x < self.dimensions.x && position.y < self.dimensions.y)
+            .then(|| position.y as usize * self.dimensions.x as usize + position.x as usize)
+    }
+    pub fn get_value(&self, position: UVec2) -> Option<T> {
+        self.index(position).map(|i| self.data[i])
+    }
+    pub fn set_value(&mut self, position: UVec2, value: T) {
+        if let Some(i) = self.index(position) {
+            self.data[i] = value;
+        }
+    }
+    /// The value at `position` without bounds checking.
+    ///
+    /// # Safety
+    /// `position` must be within [`dimensions`](CellGrid::dimensions).
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, position: UVec2) -> &mut T {
+        let i = position.y as usize * self.dimensions.x as usize + position.x as usize;
+        self.data.get_unchecked_mut(i)
+    }
+    pub fn dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+    pub fn values(&self) -> &[T] {
+        &self.data
+    }
+    pub fn row(&self, y: u32) -> Option<&[T]> {
+        let width = self.dimensions.x as usize;
+        (y < self.dimensions.y).then(|| &self.data[y as usize * width..][..width])
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.data.chunks_exact(self.dimensions.x.max(1) as usize)
     }
-    pub fn set_value(&mut self, position: UVec2, value: f32) {
-        let r = self
+    pub fn region(&self, origin: UVec2, dimensions: UVec2) -> Self {
+        //! A copy of the values in the `dimensions` sized rectangle at `origin`, cut short where
+        //! it extends past this grid.
+        let end = (origin + dimensions).min(self.dimensions);
+        let origin = origin.min(end);
+        let columns = origin.x as usize..end.x as usize;
+        Self {
+            data: (origin.y..end.y)
+                .flat_map(|y| self.row(y).unwrap()[columns.clone()].iter().copied())
+                .collect(),
+            dimensions: end - origin,
+        }
+    }
+    pub fn blit(&mut self, region: &CellGrid<T>, origin: UVec2) {
+        //! Copies `region` into this grid with its top left corner at `origin`.
+        let x = (origin.x as usize).min(self.dimensions.x as usize);
+        let width = self.dimensions.x.max(1) as usize;
+        for (row, src) in self
             .data
-            .get_mut(
-                TryInto::<usize>::try_into(position.y)
-                    .change_context_lazy(|| RasterizationError::U32ToUsize)
-                    .unwrap_or_else(|e| panic!("{}", e)),
-            )
-            .map(|v: &mut Vec<f32>| {
-                v.get_mut(
-                    TryInto::<usize>::try_into(position.x)
-                        .change_context_lazy(|| RasterizationError::U32ToUsize)
-                        .unwrap_or_else(|e| panic!("{}", e)),
-                )
-            })
-            .flatten();
-        if let Some(p) = r {
-            *p = value;
+            .chunks_exact_mut(width)
+            .skip(origin.y as usize)
+            .zip(region.rows())
+        {
+            let len = src.len().min(row.len() - x);
+            row[x..x + len].copy_from_slice(&src[..len]);
         }
     }
 }
 
+impl DepthBuffer {
+    pub fn new(dimensions: UVec2) -> Result<Self, RasterizationError> {
+        Self::filled(dimensions, f32::INFINITY)
+    }
+    pub fn clear(&mut self) {
+        self.fill(f32::INFINITY);
+    }
+}
+
 impl Rasterizer {
     pub fn clear_frame(&mut self) {
         if let Some(buf) = self.depth_buffer.as_mut() {
             buf.clear();
         }
+        if let Some(buf) = self.id_buffer.as_mut() {
+            buf.clear();
+        }
+        if let Some(buf) = self.normal_buffer.as_mut() {
+            buf.clear();
+        }
     }
     pub fn rasterize_triangle(
         &mut self,
         triangle: Triangle,
         char_buffer: &mut CharBuffer,
     ) -> Result<(), RasterizationError> {
-        if let None = self.depth_buffer {
-            self.depth_buffer = Some(DepthBuffer::new(*char_buffer.dimensions())?);
+        //! Draws a single triangle under `world_tensor`, use [`Rasterizer::draw_mesh`] for whole
+        //! meshes.
+        self.draw_triangles(
+            std::slice::from_ref(&triangle),
+            draw::Instance::new(Mat4::IDENTITY),
+            0,
+            char_buffer,
+        )
+    }
+    pub fn project_point(&mut self, rhs: Vec3A) -> Vec3 {
+        self.camera
+            .project_point(self.world_tensor.transform_point3a(rhs))
+    }
+    pub fn screen_ray(&mut self, cell: UVec2, dimensions: UVec2) -> Ray {
+        //! [`Camera::screen_ray`] brought back through `world_tensor`, into the space meshes are
+        //! given in.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let mut rasterizer = Rasterizer {
+        //!     world_tensor: Mat4::from_rotation_y(0.3),
+        //!     ..Rasterizer::default()
+        //! };
+        //! // A cube in front of the camera once placed by the world tensor
+        //! let model =
+        //!     rasterizer.world_tensor.inverse() * Mat4::from_translation(vec3(0.0, 0.0, 3.0));
+        //! let cube = SimpleMesh::cube_builder().size(1.0).build().transformed(model);
+        //! let ray = rasterizer.screen_ray(uvec2(20, 10), uvec2(40, 20));
+        //! let hit = cube.ray_intersection(&ray).unwrap();
+        //! let point = rasterizer.world_tensor.transform_point3(hit.point(&ray));
+        //! assert!((point.z - 2.5).abs() < 1e-3 && point.truncate().length() < 0.5);
+        //! // Cells at the edge of the buffer look past it
+        //! let ray = rasterizer.screen_ray(uvec2(0, 0), uvec2(40, 20));
+        //! assert!(cube.ray_intersection(&ray).is_none());
+        //! ```
+        self.camera
+            .screen_ray(cell, dimensions)
+            .transformed(self.world_tensor.inverse())
+    }
+    pub fn detect_cell_aspect(&mut self) {
+        //! Sets `cell_aspect` from the terminal's reported size, falling back to
+        //! [`DEFAULT_CELL_ASPECT`] when it doesn't report one.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let cube = SimpleMesh::cube_builder().size(1.0).build();
+        //! let model = Mat4::from_translation(vec3(0.0, 0.0, 3.0));
+        //! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! // Without a cell aspect the camera keeps its own aspect ratio
+        //! let mut rasterizer = Rasterizer {
+        //!     cell_aspect: None,
+        //!     ..Rasterizer::default()
+        //! };
+        //! rasterizer.draw_mesh(&cube, model, &mut buffer).unwrap();
+        //! assert_eq!(*rasterizer.camera.aspect_ratio(), 1.0);
+        //!
+        //! // The default square cells in a buffer twice as wide as it is tall
+        //! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+        //! let mut rasterizer = Rasterizer::default();
+        //! rasterizer.draw_mesh(&cube, model, &mut buffer).unwrap();
+        //! assert_eq!(*rasterizer.camera.aspect_ratio(), 2.0);
+        //! // The cube's front face covers as many columns as rows
+        //! let drawn = |c: &Cell| c.char != ' ';
+        //! let rows = buffer.rows().filter(|row| row.iter().any(drawn)).count();
+        //! let columns = (0..40)
+        //!     .filter(|&x| buffer.rows().any(|row| drawn(&row[x])))
+        //!     .count();
+        //! assert!(rows > 1 && rows == columns);
+        //! ```
+        self.cell_aspect = Some(terminal_cell_aspect().unwrap_or(DEFAULT_CELL_ASPECT));
+    }
+    pub(crate) fn fit_aspect_ratio(&mut self, dimensions: UVec2) {
+        //! Derives the camera's aspect ratio for a `dimensions` sized buffer from `cell_aspect`.
+        let Some(cell_aspect) = self.cell_aspect else {
+            return;
+        };
+        if dimensions.cmpeq(UVec2::ZERO).any() {
+            return;
         }
-
-        // check if triangle needs to be rendered
-        // Uses the dot product of the look dirction of the camera and the normal vector of the
-        // triangle to determine if the triangle is oriented towards the camera. The vectors
-        // should be pointing generally in opposite directions if that is the case, so the dot
-        // product should be negative.
-        let normal = triangle.normal();
-
-        if self.camera.look_dir.dot(normal.into()) >= 0.0 {
-            return Ok(());
+        let aspect_ratio = dimensions.x as f32 / dimensions.y as f32 * cell_aspect;
+        // Only invalidates the cached projection when it changes
+        if self.camera.aspect_ratio != aspect_ratio {
+            *self.camera.aspect_ratio_mut() = aspect_ratio;
         }
-
-        // calculate color after lighting
-        let mult = f32::max(normal.dot(self.light_dir.into()), 0.0)
-            * (1.0 - self.universal_lighting)
-            + self.universal_lighting;
-        let color = RgbColor(
-            (triangle.color.0 as f32 * mult) as u8,
-            (triangle.color.1 as f32 * mult) as u8,
-            (triangle.color.2 as f32 * mult) as u8,
-        );
-
-        // Project the points
-        let pv: [Vec3; 3] = triangle.v.map(|p| {
-            (self.project_point(p) + vec3(1.0, 1.0, 0.0))
-                * vec3(
-                    (char_buffer.dimensions().x + 2 >> 1) as f32,
-                    (char_buffer.dimensions().y + 2 >> 1) as f32,
-                    1.0,
-                )
-        }).map(|v: Vec3| {
-                println!("{v}           ");
-                if v.z > *self.camera.z_near() {
-                    return -v;
+    }
+    pub fn select_lod<'a>(&self, lod: &'a LodMesh, dimensions: UVec2) -> &'a SimpleMesh {
+        //! Picks the level of `lod` to draw under the current `world_tensor`, from an estimate of
+        //! how many cells of a `dimensions` sized buffer the mesh's bounding sphere covers.
+        let bounds = lod.bounds.transformed(self.world_tensor);
+        let distance = bounds.center.distance(Vec3A::from(self.camera.position));
+        if distance <= bounds.radius {
+            return &lod.levels[0];
+        }
+        let projection = self.camera.projection_matrix();
+        let w = match self.camera.projection {
+            Projection::Perspective { .. } | Projection::InfinitePerspective => {
+                // Distance to the silhouette of the sphere, as seen head on
+                (distance.powi(2) - bounds.radius.powi(2)).sqrt()
+            }
+            Projection::Orthographic { .. } | Projection::Custom(_) => {
+                let view = Mat4::look_to_rh(
+                    self.camera.position,
+                    self.camera.look_dir,
+                    self.camera.up_dir,
+                );
+                (projection * view * Vec3::from(bounds.center).extend(1.0)).w
+            }
+        };
+        if w <= 0.0 {
+            return &lod.levels[0];
+        }
+        // Radius of the sphere in normalized device coordinates
+        let ndc_radius = bounds.radius * vec2(projection.x_axis.x, projection.y_axis.y) / w;
+        let radius_cells = ndc_radius * dimensions.as_vec2() / 2.0;
+        let covered = std::f32::consts::PI * radius_cells.x * radius_cells.y;
+        &lod.levels[lod.level_for_cells(covered, self.lod_triangles_per_cell)]
+    }
+    pub fn render_scene(
+        &mut self,
+        scene: &Scene,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        //! Draws every mesh in `scene` with its node's world transform, applied after
+        //! `world_tensor` which acts as the transform of the whole scene. Renders from the scene's
+        //! active camera if it has one, and replaces `lights` with the scene's lights if it has
+        //! any. When the scene's lights cast shadows, every mesh in the scene casts them and
+        //! `shadow_maps` is made again. Otherwise the shadow maps already made are kept.
+        let transforms = scene.world_transforms();
+        let mut lights = Vec::new();
+        for (id, transform) in transforms.iter() {
+            let node = scene.node(*id).unwrap();
+            if scene.active_camera == Some(*id) {
+                if let Some(camera) = node.camera.as_ref() {
+                    self.camera = camera.posed(self.world_tensor * *transform);
                 }
-                v
-            });
-
-        if pv.iter().fold(false, |accum, v: &Vec3| (accum || v.z > *self.camera.z_near())) {
-            return Ok(());
+            }
+            if let Some(light) = node.light {
+                lights.push(light.transformed(self.world_tensor * *transform));
+            }
+        }
+        let scene_lights = !lights.is_empty();
+        if scene_lights {
+            self.lights = lights;
         }
 
-        // get fn for point on screen -> calculated z value based on projected coords
-        let depth = |p: Vec2| -> f32 {
-            let n = (pv[2] - pv[0]).cross(pv[1] - pv[0]);
-            pv[0].z - (n.x * (p.x - pv[0].x) + n.y * (p.y - pv[0].y)) / n.z
+        let meshes = || {
+            transforms.iter().filter_map(|(id, transform)| {
+                let mesh = scene.node(*id).unwrap().mesh.as_ref()?;
+                Some((mesh, *transform))
+            })
         };
-
-        // create shader including depth buffer check using closure defined above
-        let shader = |p: IVec2, _: &CharBuffer| -> (Option<char>, Option<RgbColor>) {
-            let current_depth = depth(vec2(p.x as f32, p.y as f32));
-            let old_depth = self
-                .depth_buffer
-                .as_ref()
-                .unwrap()
-                .get_value(uvec2(p.x as u32, p.y as u32))
-                .unwrap_or_else(|| panic!("{p}"));
-            if current_depth <= old_depth {
-                self.depth_buffer
-                    .as_mut()
-                    .unwrap()
-                    .set_value(uvec2(p.x as u32, p.y as u32), current_depth);
-                return (Some('.'), Some(color));
+        if scene_lights {
+            self.shadow_maps.clear();
+        }
+        if scene_lights && self.lights.iter().any(|light| light.casts_shadows) {
+            let bounds = meshes()
+                .filter_map(|(mesh, transform)| {
+                    Some(mesh.aabb()?.transformed(self.world_tensor * transform))
+                })
+                .reduce(|a, b| Aabb {
+                    min: a.min.min(b.min),
+                    max: a.max.max(b.max),
+                });
+            if let Some(bounds) = bounds {
+                self.begin_shadows(bounds.center().into(), bounds.size().length() / 2.0)?;
+                for (mesh, transform) in meshes() {
+                    self.draw_shadow_caster(mesh, transform);
+                }
             }
-            (None, None)
-        };
-
-        // draw triangle
-        char_buffer
-            .draw_triangle(pv.map(|v| ivec2(v.x as i32, v.y as i32)), shader)
-            .change_context_lazy(|| RasterizationError::TriangleRasterization)?;
+        }
 
+        for (id, transform) in transforms.iter() {
+            if let Some(mesh) = scene.node(*id).unwrap().mesh.as_ref() {
+                self.object_id = id.index() as u32;
+                self.draw_mesh(mesh, *transform, char_buffer)?;
+            }
+        }
         Ok(())
     }
-    pub fn project_point(&mut self, rhs: Vec3A) -> Vec3 {
-        self.camera
-            .project_point(self.world_tensor.transform_point3a(rhs))
+    pub fn rasterize_lod(
+        &mut self,
+        lod: &LodMesh,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        self.fit_aspect_ratio(*char_buffer.dimensions());
+        let mesh = self.select_lod(lod, *char_buffer.dimensions());
+        self.draw_mesh(mesh, Mat4::IDENTITY, char_buffer)
     }
 }
 
 impl Camera {
     pub fn project_point(&mut self, rhs: Vec3A) -> Vec3 {
-        if let None = self.perspective_tesnor {
-            self.perspective_tesnor = Some(Mat4::perspective_infinite_rh(
-                self.fov_y_radians,
-                self.aspect_ratio,
-                self.z_near,
-            ));
+        self.view_projection().project_point3(rhs.into())
+    }
+    pub fn unproject(&mut self, screen_pos: Vec2, depth: f32) -> Vec3 {
+        //! The point that [`project_point`](Camera::project_point) maps to `screen_pos` in
+        //! normalized device coordinates at `depth`.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let points = [vec3(0.0, 0.0, 2.0), vec3(0.4, -0.3, 5.0), vec3(-1.5, 0.8, 9.0)];
+        //! for projection in [
+        //!     Projection::Orthographic { height: 4.0, z_far: 10.0 },
+        //!     Projection::Perspective { z_far: 10.0 },
+        //!     Projection::InfinitePerspective,
+        //! ] {
+        //!     let mut camera = Camera::builder()
+        //!         .position(Vec3::ZERO)
+        //!         .fov_y_radians(1.0)
+        //!         .aspect_ratio(2.0)
+        //!         .z_near(0.5)
+        //!         .projection(projection)
+        //!         .build();
+        //!     for p in points {
+        //!         let ndc = camera.project_point(p.into());
+        //!         let back = camera.unproject(ndc.truncate(), ndc.z);
+        //!         assert!(back.abs_diff_eq(p, 1e-3), "{projection:?} {p} {back}");
+        //!     }
+        //! }
+        //! ```
+        self.view_projection()
+            .inverse()
+            .project_point3(screen_pos.extend(depth))
+    }
+    pub fn screen_ray(&mut self, cell: UVec2, dimensions: UVec2) -> Ray {
+        //! The ray from the near plane through the center of `cell` in a `dimensions` sized
+        //! buffer, with a direction of unit length.
+        let ndc = (cell.as_vec2() + 0.5) / screen_scale(dimensions) - Vec2::ONE;
+        let origin = self.unproject(ndc, 0.0);
+        let direction = (self.unproject(ndc, 0.5) - origin).normalize_or_zero();
+        Ray::new(origin, direction)
+    }
+    pub(crate) fn view_projection(&mut self) -> Mat4 {
+        //! The projection and view tensors combined, generating whichever isn't cached.
+        if self.projection_tensor.is_none() {
+            self.projection_tensor = Some(self.projection_matrix());
         }
-        if let None = self.view_tensor {
-            self.view_tensor = Some(Mat4::look_to_rh(self.position, self.look_dir, self.up_dir));
+        if self.view_tensor.is_none() {
+            self.generate_view();
         }
-
-        self.perspective_tesnor
-            .unwrap()
-            .project_point3(self.view_tensor.unwrap().transform_point3a(rhs).into())
+        self.projection_tensor.unwrap() * self.view_tensor.unwrap()
     }
     pub fn rotate_x_radians(&mut self, theta: f32) {
         self.rotate_self(Quat::from_rotation_x(theta));
@@ -219,6 +471,14 @@ impl Camera {
     }
 }
 
+/// Cells per unit of normalized device coordinates in a `dimensions` sized buffer.
+pub(crate) fn screen_scale(dimensions: UVec2) -> Vec2 {
+    vec2(
+        ((dimensions.x + 2) >> 1) as f32,
+        ((dimensions.y + 2) >> 1) as f32,
+    )
+}
+
 impl std::default::Default for Rasterizer {
     fn default() -> Self {
         Self {
@@ -228,9 +488,35 @@ impl std::default::Default for Rasterizer {
                 .aspect_ratio(1.0)
                 .build(),
             world_tensor: Mat4::IDENTITY,
-            light_dir: vec3(1.0, 1.0, -0.1),
-            universal_lighting: 0.6,
+            lights: vec![
+                lighting::Light::builder()
+                    .kind(lighting::LightKind::Ambient)
+                    .intensity(0.6)
+                    .build(),
+                lighting::Light::builder()
+                    .kind(lighting::LightKind::Directional {
+                        direction: vec3(-1.0, -1.0, 0.1).normalize(),
+                    })
+                    .intensity(0.4)
+                    .build(),
+            ],
+            shading: lighting::ShadingMode::Flat,
+            cel_bands: None,
+            fog: None,
+            glyph_ramp: None,
+            shadow_settings: shadows::ShadowSettings::default(),
+            shadow_maps: Vec::new(),
             depth_buffer: None,
+            id_buffer: None,
+            normal_buffer: None,
+            object_id: 0,
+            lod_triangles_per_cell: 1.0,
+            tile_size: None,
+            cull_mode: draw::CullMode::Back,
+            front_face: draw::FrontFace::CounterClockwise,
+            polygon_mode: draw::PolygonMode::Fill,
+            line_style: LineStyle::default(),
+            cell_aspect: Some(DEFAULT_CELL_ASPECT),
         }
     }
 }
@@ -245,10 +531,12 @@ impl Camera {
         fov_y_radians: f32,
         aspect_ratio: f32,
         z_near: Option<f32>,
+        projection: Option<Projection>,
     ) -> Self {
         Self {
             view_tensor: None,
-            perspective_tesnor: None,
+            projection_tensor: None,
+            projection: projection.unwrap_or_default(),
             up_dir: up_dir.unwrap_or(vec3(0.0, 1.0, 0.0)),
             look_dir: look_dir.unwrap_or(vec3(0.0, 0.0, 1.0)),
             position,
@@ -260,24 +548,67 @@ impl Camera {
     fn generate_view(&mut self) {
         self.view_tensor = Some(Mat4::look_to_rh(self.position, self.look_dir, self.up_dir));
     }
-    fn generate_perspective(&mut self) {
-        self.perspective_tesnor = Some(Mat4::perspective_infinite_rh(
-            self.fov_y_radians,
-            self.aspect_ratio,
-            self.z_near,
-        ));
+    pub fn projection_matrix(&self) -> Mat4 {
+        //! The matrix [`projection`](Camera::projection) describes with the current settings.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let points = [vec3(0.0, 0.0, 2.0), vec3(0.4, -0.3, 5.0), vec3(-1.5, 0.8, 9.0)];
+        //! for projection in [
+        //!     Projection::Orthographic { height: 4.0, z_far: 10.0 },
+        //!     Projection::Perspective { z_far: 10.0 },
+        //!     Projection::InfinitePerspective,
+        //! ] {
+        //!     let mut camera = Camera::builder()
+        //!         .position(Vec3::ZERO)
+        //!         .fov_y_radians(1.0)
+        //!         .aspect_ratio(2.0)
+        //!         .z_near(0.5)
+        //!         .projection(projection)
+        //!         .build();
+        //!     let matrix = camera.projection_matrix();
+        //!     for p in points {
+        //!         // View space looks down -Z
+        //!         let view = vec3(p.x, p.y, -p.z);
+        //!         let ndc = matrix.project_point3(view);
+        //!         assert!(ndc.z > 0.0 && ndc.z < 1.0, "{projection:?} {ndc}");
+        //!         let back = matrix.inverse().project_point3(ndc);
+        //!         assert!(back.abs_diff_eq(view, 1e-3), "{projection:?} {view} {back}");
+        //!     }
+        //!     // The near plane is at depth 0, and the far plane at depth 1 if there is one
+        //!     let near = camera.project_point(vec3a(0.0, 0.0, 0.5)).z;
+        //!     assert!(near.abs() < 1e-5, "{projection:?} {near}");
+        //!     let far = camera.project_point(vec3a(0.0, 0.0, 10.0)).z;
+        //!     match projection {
+        //!         Projection::InfinitePerspective => assert!(far < 1.0),
+        //!         _ => assert!((far - 1.0).abs() < 1e-5, "{projection:?} {far}"),
+        //!     }
+        //! }
+        //! ```
+        match self.projection {
+            Projection::Perspective { z_far } => {
+                Mat4::perspective_rh(self.fov_y_radians, self.aspect_ratio, self.z_near, z_far)
+            }
+            Projection::InfinitePerspective => {
+                Mat4::perspective_infinite_rh(self.fov_y_radians, self.aspect_ratio, self.z_near)
+            }
+            Projection::Orthographic { height, z_far } => {
+                let half = vec2(height * self.aspect_ratio, height) / 2.0;
+                Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, self.z_near, z_far)
+            }
+            Projection::Custom(matrix) => matrix,
+        }
     }
     pub fn up_dir(&self) -> &Vec3 {
         &self.up_dir
     }
-    pub fn up_dir_mut(&mut self) -> &Vec3 {
+    pub fn up_dir_mut(&mut self) -> &mut Vec3 {
         self.view_tensor = None;
         &mut self.up_dir
     }
     pub fn look_dir(&self) -> &Vec3 {
         &self.look_dir
     }
-    pub fn look_dir_mut(&mut self) -> &Vec3 {
+    pub fn look_dir_mut(&mut self) -> &mut Vec3 {
         self.view_tensor = None;
         &mut self.look_dir
     }
@@ -292,23 +623,49 @@ impl Camera {
         &self.fov_y_radians
     }
     pub fn fov_y_radians_mut(&mut self) -> &mut f32 {
-        self.perspective_tesnor = None;
+        self.projection_tensor = None;
         &mut self.fov_y_radians
     }
     pub fn aspect_ratio(&self) -> &f32 {
         &self.aspect_ratio
     }
     pub fn aspect_ratio_mut(&mut self) -> &mut f32 {
-        self.perspective_tesnor = None;
+        self.projection_tensor = None;
         &mut self.aspect_ratio
     }
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+    pub fn projection_mut(&mut self) -> &mut Projection {
+        self.projection_tensor = None;
+        &mut self.projection
+    }
     pub fn z_near(&self) -> &f32 {
         &self.z_near
     }
     pub fn z_near_mut(&mut self) -> &mut f32 {
-        self.perspective_tesnor = None;
+        self.projection_tensor = None;
         &mut self.z_near
     }
+    pub(crate) fn posed(&self, transform: Mat4) -> Self {
+        //! This camera moved from its own space into the space `transform` maps to.
+        Self {
+            view_tensor: None,
+            position: transform.transform_point3(self.position),
+            look_dir: transform.transform_vector3(self.look_dir).normalize(),
+            up_dir: transform.transform_vector3(self.up_dir).normalize(),
+            ..self.clone()
+        }
+    }
+    pub fn look_at(&mut self, target: Vec3) {
+        //! Turns the camera to face `target`, leaving it as it is if `target` is its position.
+        //! `up_dir` is kept, so `target` shouldn't be straight above or below the camera.
+        let dir = (target - self.position).normalize_or_zero();
+        if dir != Vec3::ZERO {
+            self.view_tensor = None;
+            self.look_dir = dir;
+        }
+    }
     pub fn rotate_self(&mut self, rotate: Quat) {
         self.view_tensor = None;
         self.look_dir = rotate * self.look_dir;
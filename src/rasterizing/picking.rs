@@ -0,0 +1,98 @@
+//! Object IDs per cell, recorded alongside depth while rasterizing so tools can tell exactly
+//! which mesh, instance and triangle ended up in each cell of a frame.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::rasterizing::picking::IdBuffer;
+//! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+//! let mut rasterizer = Rasterizer {
+//!     id_buffer: Some(IdBuffer::new(uvec2(40, 20)).unwrap()),
+//!     object_id: 7,
+//!     ..Rasterizer::default()
+//! };
+//! let cube = SimpleMesh::cube_builder().size(0.5).build();
+//! rasterizer
+//!     .draw_mesh(&cube, Mat4::from_translation(vec3(0.0, 0.0, 3.0)), &mut buffer)
+//!     .unwrap();
+//! let ids = rasterizer.id_buffer.as_ref().unwrap();
+//! assert_eq!(ids.get(uvec2(20, 10)).map(|id| id.object), Some(7));
+//! assert_eq!(ids.get(uvec2(0, 0)), None);
+//!
+//! buffer.draw_outline(ids, |id| id.object == 7, |_, _| (Some('#'), None));
+//! assert_eq!(buffer.get_char(uvec2(0, 0)).unwrap().0, ' ');
+//! assert!(buffer.cells().iter().any(|c| c.char == '#'));
+//!
+//! // Edges record IDs too, in every cell they are drawn to
+//! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+//! rasterizer.polygon_mode = PolygonMode::Wireframe;
+//! rasterizer.clear_frame();
+//! rasterizer
+//!     .draw_mesh(&cube, Mat4::from_translation(vec3(0.0, 0.0, 3.0)), &mut buffer)
+//!     .unwrap();
+//! let ids = rasterizer.id_buffer.as_ref().unwrap();
+//! for (p, cell) in buffer.positions() {
+//!     assert_eq!(cell.char != ' ', ids.get(p).is_some());
+//! }
+//! ```
+pub(crate) use super::*;
+
+/// What was drawn to a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellId {
+    /// [`Rasterizer::object_id`] when the cell was drawn.
+    pub object: u32,
+    /// Index of the instance in [`Rasterizer::draw_mesh_instanced`], 0 for other draws.
+    pub instance: u32,
+    /// Index of the triangle in its mesh.
+    pub triangle: u32,
+}
+
+/// The [`CellId`] of the closest fragment drawn to each cell.
+pub type IdBuffer = CellGrid<Option<CellId>>;
+
+impl IdBuffer {
+    pub fn new(dimensions: UVec2) -> Result<Self, RasterizationError> {
+        Self::filled(dimensions, None)
+    }
+    pub fn clear(&mut self) {
+        self.fill(None);
+    }
+    pub fn get(&self, position: UVec2) -> Option<CellId> {
+        //! The ID of what was drawn at `position`, `None` if nothing was or it is out of range.
+        self.get_value(position).flatten()
+    }
+    pub fn outline(&self, selected: impl Fn(CellId) -> bool) -> Vec<UVec2> {
+        //! Cells just outside the selected ones: those not selected themselves that share a side
+        //! with a selected cell.
+        let is_selected = |p: IVec2| {
+            (p.cmpge(IVec2::ZERO).all() && p.cmplt(self.dimensions.as_ivec2()).all())
+                .then(|| self.get(p.as_uvec2()))
+                .flatten()
+                .is_some_and(&selected)
+        };
+        (0..self.dimensions.y)
+            .flat_map(|y| (0..self.dimensions.x).map(move |x| uvec2(x, y)))
+            .filter(|p| {
+                let p = p.as_ivec2();
+                !is_selected(p)
+                    && [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+                        .into_iter()
+                        .any(|d| is_selected(p + d))
+            })
+            .collect()
+    }
+}
+
+impl CharBuffer {
+    pub fn draw_outline(
+        &mut self,
+        ids: &IdBuffer,
+        selected: impl Fn(CellId) -> bool,
+        mut shading: impl FnMut(IVec2, &CharBuffer) -> Shade,
+    ) {
+        //! Shades the cells around everything `selected` accepts in a frame drawn with `ids`.
+        for p in ids.outline(selected) {
+            let shade = shading(p.as_ivec2(), self);
+            self.blend_shade(p, shade, 1.0);
+        }
+    }
+}
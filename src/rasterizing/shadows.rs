@@ -0,0 +1,259 @@
+//! Shadow mapping. Before the main pass, every shadow casting directional and spot light renders
+//! the depth of the shadow casters as it sees them into a [`ShadowMap`]. Surfaces further from the
+//! light than what its map recorded are in shadow, and don't receive that light.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! let mut rasterizer = Rasterizer {
+//!     lights: vec![Light::builder()
+//!         .kind(LightKind::Directional { direction: vec3(0.0, 0.0, 1.0) })
+//!         .casts_shadows(true)
+//!         .build()],
+//!     ..Rasterizer::default()
+//! };
+//! let cube = SimpleMesh::cube_builder().size(1.0).build();
+//! let (front, back) = (
+//!     Mat4::from_translation(vec3(0.0, 0.0, 3.0)),
+//!     Mat4::from_translation(vec3(0.0, 0.0, 6.0)) * Mat4::from_scale(Vec3::splat(4.0)),
+//! );
+//! rasterizer.begin_shadows(vec3(0.0, 0.0, 4.5), 8.0).unwrap();
+//! rasterizer.draw_shadow_caster(&cube, front);
+//! rasterizer.draw_shadow_caster(&cube, back);
+//! let map = rasterizer.shadow_maps[0].as_ref().unwrap();
+//! // The front cube shadows the middle of the back one, but not its edges
+//! assert_eq!(map.visibility(vec3(0.0, 0.0, 3.99)), 0.0);
+//! assert_eq!(map.visibility(vec3(1.5, 0.0, 3.99)), 1.0);
+//!
+//! // Casters in front of the map's depth range still cast their shadow
+//! rasterizer.begin_shadows(vec3(0.0, 0.0, 6.0), 1.0).unwrap();
+//! rasterizer.draw_shadow_caster(&cube, front);
+//! let map = rasterizer.shadow_maps[0].as_ref().unwrap();
+//! assert_eq!(map.visibility(vec3(0.0, 0.0, 6.0)), 0.0);
+//!
+//! // Scenes without lights of their own keep the maps made for the rasterizer's lights
+//! let mut scene = Scene::new();
+//! scene.add_node(Node::builder().mesh(cube.clone()).build(), None).unwrap();
+//! let mut buffer = CharBuffer::new(uvec2(40, 20), ' ', RgbColor(0, 0, 0)).unwrap();
+//! rasterizer.render_scene(&scene, &mut buffer).unwrap();
+//! assert!(rasterizer.shadow_maps[0].is_some());
+//! ```
+pub(crate) use super::*;
+use crate::printing::drawing::{for_each_covered_cell, to_fixed};
+use lighting::LightKind;
+
+/// How shadow maps are made and sampled. Shadows are sampled wherever lighting is worked out, so
+/// with [`ShadingMode::Flat`] a triangle is shadowed as a whole by the visibility at its center,
+/// and shadow edges follow the triangles. Use [`ShadingMode::PerPixel`] for shadows sampled at
+/// every cell.
+///
+/// [`ShadingMode::Flat`]: lighting::ShadingMode::Flat
+/// [`ShadingMode::PerPixel`]: lighting::ShadingMode::PerPixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Cells along each side of a shadow map.
+    pub resolution: u32,
+    /// How far, in world units, a surface may be behind the closest caster its light sees and
+    /// still be lit. Keeps surfaces from shadowing themselves.
+    pub bias: f32,
+    /// Radius in shadow map cells of the samples averaged for soft edges, 0 takes a single
+    /// sample.
+    pub pcf_radius: u32,
+}
+
+/// The depth of the closest shadow caster a light sees in every direction.
+#[derive(Debug, Clone)]
+pub struct ShadowMap {
+    /// The light the map was made for. Lighting only uses the map for this exact light, so a
+    /// light that moved or changed since gets no shadows until its map is made again.
+    light: Light,
+    pub depth: DepthBuffer,
+    /// Maps world space into the light's normalized device coordinates.
+    pub view_projection: Mat4,
+    projection: Mat4,
+    bias: f32,
+    pcf_radius: i32,
+}
+
+#[buildstructor]
+impl ShadowSettings {
+    #[builder(visibility = "pub")]
+    pub fn new(resolution: Option<u32>, bias: Option<f32>, pcf_radius: Option<u32>) -> Self {
+        Self {
+            resolution: resolution.unwrap_or(256),
+            bias: bias.unwrap_or(0.05),
+            pcf_radius: pcf_radius.unwrap_or(1),
+        }
+    }
+}
+
+impl std::default::Default for ShadowSettings {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl ShadowMap {
+    pub fn new(
+        light: &Light,
+        center: Vec3,
+        radius: f32,
+        settings: &ShadowSettings,
+    ) -> Result<Option<Self>, RasterizationError> {
+        //! A map covering the sphere at `center` for a shadow casting directional or spot light,
+        //! `None` for other lights.
+        let radius = radius.max(f32::EPSILON);
+        let (view, projection) = match light.kind {
+            _ if !light.casts_shadows => return Ok(None),
+            LightKind::Directional { direction } => {
+                let direction = direction.normalize_or_zero();
+                let eye = center - direction * 2.0 * radius;
+                let view = Mat4::look_to_rh(eye, direction, up_for(direction));
+                let projection = Mat4::orthographic_rh(
+                    -radius,
+                    radius,
+                    -radius,
+                    radius,
+                    radius * 0.9,
+                    radius * 3.1,
+                );
+                (view, projection)
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                outer_angle,
+                ..
+            } => {
+                let direction = direction.normalize_or_zero();
+                let view = Mat4::look_to_rh(position, direction, up_for(direction));
+                let z_far = position.distance(center) + radius;
+                let z_near = (z_far - 2.0 * radius).max(z_far * 0.01);
+                let fov = (2.0 * outer_angle).clamp(0.01, std::f32::consts::PI - 0.01);
+                (view, Mat4::perspective_rh(fov, 1.0, z_near, z_far))
+            }
+            LightKind::Ambient | LightKind::Point { .. } => return Ok(None),
+        };
+        Ok(Some(Self {
+            light: *light,
+            depth: DepthBuffer::new(UVec2::splat(settings.resolution))?,
+            view_projection: projection * view,
+            projection,
+            bias: settings.bias,
+            pcf_radius: settings.pcf_radius as i32,
+        }))
+    }
+    pub fn light(&self) -> &Light {
+        &self.light
+    }
+    fn to_map(&self, position: Vec3) -> Option<Vec3> {
+        //! `position` in map cells, with its depth, if the light's view volume contains it.
+        let clip = self.view_projection * position.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        let size = self.depth.dimensions().as_vec2();
+        Some(((ndc.truncate() + Vec2::ONE) * 0.5 * size).extend(ndc.z))
+    }
+    fn distance(&self, depth: f32) -> f32 {
+        //! How far in front of the light a depth in the map is, undoing the projection.
+        let p = self.projection;
+        let (a, b, c, d) = (p.z_axis.z, p.w_axis.z, p.z_axis.w, p.w_axis.w);
+        -(b - depth * d) / (depth * c - a)
+    }
+    pub fn draw_caster(&mut self, triangles: &[Triangle], model: Mat4) {
+        //! Records the depth of `triangles` placed by `model`, from both sides. Casters beyond the
+        //! map's depth range are flattened onto its near or far plane rather than dropped, so
+        //! they still shadow what lies behind them.
+        let dimensions = self.depth.dimensions();
+        let transform = self.view_projection * model;
+        for triangle in triangles {
+            let mut v = [Vec3::ZERO; 3];
+            for (out, p) in v.iter_mut().zip(triangle.v) {
+                let clip = transform * Vec3::from(p).extend(1.0);
+                // Only spot lights have anything behind them, which they can't see
+                if clip.w <= 0.0 {
+                    v[0].z = f32::NAN;
+                    break;
+                }
+                let ndc = clip.truncate() / clip.w;
+                let cell = (ndc.truncate() + Vec2::ONE) * 0.5 * dimensions.as_vec2();
+                *out = cell.extend(ndc.z.clamp(0.0, 1.0));
+            }
+            if v[0].z.is_nan() {
+                continue;
+            }
+            let n = (v[2] - v[0]).cross(v[1] - v[0]);
+            if n.z == 0.0 {
+                continue;
+            }
+            let depth = |p: Vec2| v[0].z - (n.x * (p.x - v[0].x) + n.y * (p.y - v[0].y)) / n.z;
+            for_each_covered_cell(dimensions, v.map(|v| to_fixed(v.truncate())), |cell| {
+                let d = depth(cell.as_vec2() + 0.5);
+                // SAFETY: only cells within the map are covered
+                let closest = unsafe { self.depth.get_unchecked_mut(cell.as_uvec2()) };
+                if d < *closest {
+                    *closest = d;
+                }
+            });
+        }
+    }
+    pub fn visibility(&self, position: Vec3) -> f32 {
+        //! How much of the light reaches `position`, from 0 in full shadow to 1 fully lit.
+        //! Anything outside the map is lit.
+        let Some(p) = self.to_map(position) else {
+            return 1.0;
+        };
+        let size = self.depth.dimensions().as_ivec2();
+        if !(0.0..=1.0).contains(&p.z) || p.x < 0.0 || p.y < 0.0 {
+            return 1.0;
+        }
+        let cell = p.truncate().floor().as_ivec2();
+        if cell.x >= size.x || cell.y >= size.y {
+            return 1.0;
+        }
+        let distance = self.distance(p.z) - self.bias;
+        let r = self.pcf_radius;
+        let mut shadowed = 0;
+        for y in -r..=r {
+            for x in -r..=r {
+                let sample = (cell + ivec2(x, y)).clamp(IVec2::ZERO, size - IVec2::ONE);
+                let closest = self.depth.get_value(sample.as_uvec2()).unwrap();
+                if closest.is_finite() && distance > self.distance(closest) {
+                    shadowed += 1;
+                }
+            }
+        }
+        1.0 - shadowed as f32 / ((2 * r + 1) * (2 * r + 1)) as f32
+    }
+}
+
+impl Rasterizer {
+    pub fn begin_shadows(&mut self, center: Vec3, radius: f32) -> Result<(), RasterizationError> {
+        //! Starts new shadow maps for the shadow casting lights, covering casters and receivers
+        //! within `radius` of `center` in world space. Draw the casters with
+        //! [`Rasterizer::draw_shadow_caster`] before drawing the frame.
+        self.shadow_maps = self
+            .lights
+            .iter()
+            .map(|light| ShadowMap::new(light, center, radius, &self.shadow_settings))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+    pub fn draw_shadow_caster(&mut self, mesh: &SimpleMesh, model: Mat4) {
+        //! Adds `mesh` placed by `model`, which is applied before `world_tensor`, to every shadow
+        //! map.
+        let model = self.world_tensor * model;
+        for map in self.shadow_maps.iter_mut().flatten() {
+            map.draw_caster(&mesh.triangles, model);
+        }
+    }
+}
+
+/// An up direction for looking along `direction`.
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.cross(Vec3::Y).length_squared() > 1e-6 {
+        Vec3::Y
+    } else {
+        Vec3::X
+    }
+}
@@ -0,0 +1,144 @@
+//! Parallel rasterization. The frame is split into tiles, each projected triangle is binned into
+//! the tiles its bounding box overlaps, and the tiles are rasterized on the rayon thread pool
+//! into copies of their region of the frame before being copied back.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::rasterizing::{edges::NormalBuffer, picking::IdBuffer};
+//! let sphere = SimpleMesh::uv_sphere_builder().radius(0.8).build();
+//! let cube = SimpleMesh::cube_builder().size(1.0).build();
+//! let render = |tile_size| {
+//!     let dimensions = uvec2(41, 23);
+//!     let mut buffer = CharBuffer::new(dimensions, ' ', RgbColor(0, 0, 0)).unwrap();
+//!     let mut rasterizer = Rasterizer {
+//!         tile_size,
+//!         shading: ShadingMode::PerPixel,
+//!         id_buffer: Some(IdBuffer::new(dimensions).unwrap()),
+//!         normal_buffer: Some(NormalBuffer::new(dimensions).unwrap()),
+//!         ..Rasterizer::default()
+//!     };
+//!     let models = [
+//!         Mat4::from_translation(vec3(0.4, 0.2, 3.0)) * Mat4::from_rotation_y(0.7),
+//!         Mat4::from_translation(vec3(-0.5, 0.0, 3.5)),
+//!     ];
+//!     rasterizer.draw_mesh(&cube, models[0], &mut buffer).unwrap();
+//!     rasterizer.object_id = 1;
+//!     rasterizer.draw_mesh(&sphere, models[1], &mut buffer).unwrap();
+//!     (
+//!         buffer.cells().clone(),
+//!         rasterizer.depth_buffer.unwrap().values().to_vec(),
+//!         rasterizer.id_buffer.unwrap().values().to_vec(),
+//!         rasterizer.normal_buffer.unwrap().values().to_vec(),
+//!     )
+//! };
+//! let (serial, tiled) = (render(None), render(Some(uvec2(7, 5))));
+//! assert!(serial.2.iter().any(|id| id.is_some_and(|id| id.object == 1)));
+//! assert!(serial.0 == tiled.0, "cells differ");
+//! assert!(serial.1 == tiled.1, "depths differ");
+//! assert!(serial.2 == tiled.2, "IDs differ");
+//! assert!(serial.3 == tiled.3, "normals differ");
+//! ```
+pub(crate) use super::*;
+use draw::{rasterize_screen_triangle, CellRecords, FragmentShading, ScreenTriangle};
+use rayon::prelude::*;
+
+impl Rasterizer {
+    pub(crate) fn raster_tiles(
+        &mut self,
+        triangles: &[ScreenTriangle],
+        tile_size: UVec2,
+        depth_only: bool,
+        char_buffer: &mut CharBuffer,
+    ) -> Result<(), RasterizationError> {
+        let dimensions = *char_buffer.dimensions();
+        let tile_size = tile_size.max(UVec2::ONE);
+        let tiles = (dimensions + tile_size - UVec2::ONE) / tile_size;
+        let bins = bin_triangles(triangles, dimensions, tile_size, tiles);
+
+        let depth_buffer = self.depth_buffer.as_mut().unwrap();
+        let mut id_buffer = self.id_buffer.as_mut();
+        let mut normal_buffer = self.normal_buffer.as_mut();
+        let (frame, depths): (&CharBuffer, &DepthBuffer) = (char_buffer, depth_buffer);
+        let (ids, normals) = (id_buffer.as_deref(), normal_buffer.as_deref());
+        let shading = FragmentShading::new(
+            &self.lights,
+            &self.shadow_maps,
+            self.cel_bands,
+            self.fog.as_ref(),
+            self.glyph_ramp.as_deref(),
+            &self.camera,
+        );
+        let rendered = bins
+            .par_iter()
+            .enumerate()
+            .filter(|(_, bin)| !bin.is_empty())
+            .map(|(i, bin)| {
+                let origin = uvec2(i as u32 % tiles.x, i as u32 / tiles.x) * tile_size;
+                let mut tile = frame.region(origin, tile_size);
+                let mut tile_depths = depths.region(origin, tile_size);
+                let mut tile_ids = ids.map(|ids| ids.region(origin, tile_size));
+                let mut tile_normals = normals.map(|normals| normals.region(origin, tile_size));
+                // Bins keep the draw order, so ties in the depth test resolve like they do serially
+                bin.iter().try_for_each(|t| {
+                    rasterize_screen_triangle(
+                        &triangles[*t],
+                        origin.as_ivec2(),
+                        depth_only,
+                        shading,
+                        &mut tile,
+                        &mut tile_depths,
+                        CellRecords {
+                            ids: tile_ids.as_mut(),
+                            normals: tile_normals.as_mut(),
+                        },
+                    )
+                })?;
+                Ok((origin, tile, tile_depths, tile_ids, tile_normals))
+            })
+            .collect::<Result<Vec<_>, RasterizationError>>()?;
+
+        for (origin, tile, tile_depths, tile_ids, tile_normals) in rendered {
+            char_buffer.blit(&tile, origin);
+            depth_buffer.blit(&tile_depths, origin);
+            if let (Some(ids), Some(tile_ids)) = (id_buffer.as_deref_mut(), tile_ids) {
+                ids.blit(&tile_ids, origin);
+            }
+            if let (Some(normals), Some(tiles)) = (normal_buffer.as_deref_mut(), tile_normals) {
+                normals.blit(&tiles, origin);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bin_triangles(
+    triangles: &[ScreenTriangle],
+    dimensions: UVec2,
+    tile_size: UVec2,
+    tiles: UVec2,
+) -> Vec<Vec<usize>> {
+    //! Indices of the triangles overlapping each tile, in row major tile order.
+    let mut bins = vec![Vec::new(); (tiles.x * tiles.y) as usize];
+    for (i, triangle) in triangles.iter().enumerate() {
+        // Cells are covered by their centers, so a cell can only be covered if its center is
+        // within the triangle's bounds. Rounded outwards as vertices get snapped to subpixels.
+        let v = triangle.v.map(|v| v.truncate());
+        let min = (v[0].min(v[1]).min(v[2]) - 0.5)
+            .floor()
+            .as_ivec2()
+            .max(IVec2::ZERO);
+        let max = (v[0].max(v[1]).max(v[2]) - 0.5)
+            .ceil()
+            .as_ivec2()
+            .min(dimensions.as_ivec2() - IVec2::ONE);
+        if min.cmpgt(max).any() {
+            continue;
+        }
+        let (min, max) = (min.as_uvec2() / tile_size, max.as_uvec2() / tile_size);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                bins[(y * tiles.x + x) as usize].push(i);
+            }
+        }
+    }
+    bins
+}
@@ -0,0 +1,493 @@
+pub(crate) use super::*;
+use ::gltf::{image::Format, mesh::Mode};
+use std::path::Path;
+
+/// A glTF scene: the node hierarchy of the default scene along with every mesh, material and
+/// texture in the file. Nodes, meshes, materials and textures refer to each other by index.
+#[derive(Debug, Clone)]
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// Nodes at the top of the hierarchy of the default scene.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<Rc<Texture>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// One primitive of a glTF mesh. The material's base color has already been applied to the
+/// triangles, sampled at each vertex when the material has a texture.
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    pub mesh: SimpleMesh,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    /// Linear RGBA base color factor.
+    pub base_color_factor: Vec4,
+    /// Index into [`GltfScene::textures`].
+    pub base_color_texture: Option<usize>,
+}
+
+impl GltfScene {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ResourceError> {
+        //! Loads a `.gltf` or `.glb` file. External buffers and images are resolved relative to
+        //! the file, embedded base64 and binary chunk data is read directly.
+        let path = path.as_ref();
+        let (document, buffers, images) = ::gltf::import(path)
+            .change_context_lazy(|| ResourceError::GltfLoadError)
+            .attach_printable_lazy(|| format!("could not import {}", path.display()))?;
+        Self::from_import(document, &buffers, &images)
+    }
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ResourceError> {
+        //! Loads a `.gltf` or `.glb` file from memory. All buffers and images have to be
+        //! embedded, since there is no path to resolve external files against.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::resources::gltf::GltfScene;
+        //! // A root node holding a child with a single triangle
+        //! let json = r#"{
+        //!     "asset": {"version": "2.0"},
+        //!     "scene": 0,
+        //!     "scenes": [{"nodes": [0]}],
+        //!     "nodes": [
+        //!         {"name": "root", "translation": [0, 0, 5], "children": [1]},
+        //!         {"name": "child", "translation": [1, 0, 0], "scale": [2, 2, 2], "mesh": 0}
+        //!     ],
+        //!     "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+        //!     "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0.5, 0, 1]}}],
+        //!     "accessors": [{
+        //!         "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+        //!         "min": [0, 0, 0], "max": [1, 1, 0]
+        //!     }],
+        //!     "bufferViews": [{"buffer": 0, "byteLength": 36}],
+        //!     "buffers": [BUFFER]
+        //! }"#;
+        //! let check = |scene: GltfScene| {
+        //!     assert_eq!(scene.roots, [0]);
+        //!     assert_eq!(scene.nodes[0].children, [1]);
+        //!     assert_eq!(scene.nodes[1].name.as_deref(), Some("child"));
+        //!     let mesh = scene.flatten();
+        //!     assert_eq!(mesh.triangles.len(), 1);
+        //!     let triangle = mesh.triangles[0];
+        //!     // Scaled by the child, then moved by both nodes
+        //!     assert!(triangle.v.contains(&vec3a(3.0, 0.0, 5.0)));
+        //!     assert!(triangle.v.contains(&vec3a(1.0, 2.0, 5.0)));
+        //!     // The linear base color factor is baked in as sRGB
+        //!     assert_eq!(triangle.color, RgbColor(255, 186, 0));
+        //! };
+        //! let base64 = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA";
+        //! let uri = format!(r#"{{"byteLength": 36, "uri": "data:;base64,{base64}"}}"#);
+        //! check(GltfScene::from_slice(json.replace("BUFFER", &uri).as_bytes()).unwrap());
+        //!
+        //! // The same scene as a binary .glb, with the positions in its binary chunk
+        //! let mut json = json.replace("BUFFER", r#"{"byteLength": 36}"#).into_bytes();
+        //! json.resize(json.len().next_multiple_of(4), b' ');
+        //! let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        //!     .iter()
+        //!     .flat_map(|f| f.to_le_bytes())
+        //!     .collect();
+        //! let length = (12 + 8 + json.len() + 8 + positions.len()) as u32;
+        //! let glb = [
+        //!     &b"glTF"[..], &2u32.to_le_bytes(), &length.to_le_bytes(),
+        //!     &(json.len() as u32).to_le_bytes(), b"JSON", &json,
+        //!     &(positions.len() as u32).to_le_bytes(), b"BIN\0", &positions,
+        //! ]
+        //! .concat();
+        //! check(GltfScene::from_slice(&glb).unwrap());
+        //!
+        //! // Nodes have to form a forest
+        //! let load = |nodes: &str| {
+        //!     let json = format!(r#"{{"asset": {{"version": "2.0"}}, "nodes": {nodes}}}"#);
+        //!     GltfScene::from_slice(json.as_bytes())
+        //! };
+        //! assert!(load(r#"[{"children": [1]}, {}]"#).is_ok());
+        //! // A node with two parents, and two nodes that are each other's parent
+        //! assert!(load(r#"[{"children": [2]}, {"children": [2]}, {}]"#).is_err());
+        //! assert!(load(r#"[{}, {"children": [2]}, {"children": [1]}]"#).is_err());
+        //! ```
+        let (document, buffers, images) =
+            ::gltf::import_slice(bytes).change_context_lazy(|| ResourceError::GltfLoadError)?;
+        Self::from_import(document, &buffers, &images)
+    }
+    fn from_import(
+        document: ::gltf::Document,
+        buffers: &[::gltf::buffer::Data],
+        images: &[::gltf::image::Data],
+    ) -> Result<Self, ResourceError> {
+        let textures = images
+            .iter()
+            .map(|image| Rc::new(texture_from_image(image)))
+            .collect::<Vec<_>>();
+
+        let materials = document
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                GltfMaterial {
+                    name: material.name().map(str::to_owned),
+                    base_color_factor: Vec4::from_array(pbr.base_color_factor()),
+                    base_color_texture: pbr
+                        .base_color_texture()
+                        .map(|info| info.texture().source().index()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                Ok(GltfMesh {
+                    name: mesh.name().map(str::to_owned),
+                    primitives: mesh
+                        .primitives()
+                        .filter(|p| {
+                            matches!(
+                                p.mode(),
+                                Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
+                            )
+                        })
+                        .map(|primitive| {
+                            read_primitive(&primitive, buffers, &textures).attach_printable_lazy(
+                                || {
+                                    format!(
+                                        "in primitive {} of mesh {}",
+                                        primitive.index(),
+                                        mesh.index()
+                                    )
+                                },
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ResourceError>>()?;
+
+        let nodes = document
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                GltfNode {
+                    name: node.name().map(str::to_owned),
+                    translation: Vec3::from_array(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from_array(scale),
+                    children: node.children().map(|c| c.index()).collect(),
+                    mesh: node.mesh().map(|m| m.index()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let roots: Vec<_> = match document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            Some(scene) => scene.nodes().map(|n| n.index()).collect(),
+            // Files without scenes still have nodes, so every node without a parent is a root
+            None => (0..nodes.len())
+                .filter(|i| !nodes.iter().any(|n| n.children.contains(i)))
+                .collect(),
+        };
+
+        check_hierarchy(&nodes, &roots)?;
+
+        Ok(Self {
+            nodes,
+            roots,
+            meshes,
+            materials,
+            textures,
+        })
+    }
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        //! The transform from each node's space into world space, indexed like [`Self::nodes`].
+        //! Nodes that aren't part of the default scene keep the identity.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::resources::gltf::{GltfNode, GltfScene};
+        //! let node = |children: Vec<usize>| GltfNode {
+        //!     name: None,
+        //!     translation: vec3(1.0, 0.0, 0.0),
+        //!     rotation: Quat::IDENTITY,
+        //!     scale: Vec3::ONE,
+        //!     children,
+        //!     mesh: None,
+        //! };
+        //! // Node 2 is listed under both 0 and 1, and 1 and 3 list each other
+        //! let scene = GltfScene {
+        //!     nodes: vec![node(vec![1, 2]), node(vec![2, 3]), node(vec![]), node(vec![1])],
+        //!     roots: vec![0],
+        //!     meshes: vec![],
+        //!     materials: vec![],
+        //!     textures: vec![],
+        //! };
+        //! // Every node is placed once, under the first parent it's reached through
+        //! let x = scene.world_transforms().iter().map(|m| m.w_axis.x).collect::<Vec<_>>();
+        //! assert_eq!(x, [1.0, 2.0, 2.0, 3.0]);
+        //! assert!(scene.flatten().triangles.is_empty());
+        //! ```
+        let mut transforms = vec![Mat4::IDENTITY; self.nodes.len()];
+        for (i, transform) in self.scene_nodes() {
+            transforms[i] = transform;
+        }
+        transforms
+    }
+    pub fn flatten(&self) -> SimpleMesh {
+        //! Bakes every mesh instance of the default scene into a single world space mesh.
+        let mut triangles = Vec::new();
+        for (i, transform) in self.scene_nodes() {
+            let node = &self.nodes[i];
+            if let Some(mesh) = node.mesh.and_then(|m| self.meshes.get(m)) {
+                triangles.extend(
+                    mesh.primitives
+                        .iter()
+                        .flat_map(|p| p.mesh.triangles.iter().map(|t| t.transformed(transform))),
+                );
+            }
+        }
+        triangles.into_iter().collect()
+    }
+    fn scene_nodes(&self) -> Vec<(usize, Mat4)> {
+        //! Every node of the default scene with its world transform, parents before their
+        //! children. The fields are public, so a node reached a second time, through a shared
+        //! child or a cycle, is skipped rather than walked again.
+        let mut visited = vec![false; self.nodes.len()];
+        let mut nodes = Vec::new();
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|i| (*i, Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+        while let Some((i, parent)) = stack.pop() {
+            let Some(node) = self.nodes.get(i) else {
+                continue;
+            };
+            if std::mem::replace(&mut visited[i], true) {
+                continue;
+            }
+            let transform = parent * node.local_transform();
+            nodes.push((i, transform));
+            stack.extend(node.children.iter().map(|c| (*c, transform)));
+        }
+        nodes
+    }
+}
+
+fn check_hierarchy(nodes: &[GltfNode], roots: &[usize]) -> Result<(), ResourceError> {
+    //! glTF requires the nodes to form a forest: every node has at most one parent, there are
+    //! no cycles, and scene roots have no parent.
+    let report = |msg: String| Report::new(ResourceError::GltfLoadError).attach_printable(msg);
+    let mut parents = vec![None; nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for &child in &node.children {
+            match parents.get_mut(child) {
+                None => return Err(report(format!("node {i} has a missing child {child}"))),
+                Some(Some(parent)) => {
+                    return Err(report(format!(
+                        "node {child} is a child of both node {parent} and node {i}"
+                    )))
+                }
+                Some(parent) => *parent = Some(i),
+            }
+        }
+    }
+    if let Some(root) = roots.iter().find(|r| parents.get(**r) != Some(&None)) {
+        return Err(report(format!(
+            "scene root {root} is missing or has a parent"
+        )));
+    }
+    // With at most one parent each, only nodes on a cycle can't be reached from the top
+    let mut stack = (0..nodes.len())
+        .filter(|i| parents[*i].is_none())
+        .collect::<Vec<_>>();
+    let mut reached = 0;
+    while let Some(i) = stack.pop() {
+        reached += 1;
+        stack.extend(&nodes[i].children);
+    }
+    if reached < nodes.len() {
+        return Err(report("the node hierarchy has a cycle".to_owned()));
+    }
+    Ok(())
+}
+
+impl GltfNode {
+    pub fn local_transform(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+fn read_primitive(
+    primitive: &::gltf::Primitive,
+    buffers: &[::gltf::buffer::Data],
+    textures: &[Rc<Texture>],
+) -> Result<GltfPrimitive, ResourceError> {
+    let report =
+        |msg: &str| Report::new(ResourceError::GltfLoadError).attach_printable(msg.to_owned());
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d.0[..]));
+
+    let positions = reader
+        .read_positions()
+        .ok_or_else(|| report("primitive has no positions"))?
+        .map(Vec3A::from_array)
+        .collect::<Vec<_>>();
+    let normals = reader
+        .read_normals()
+        .map(|n| n.map(Vec3A::from_array).collect::<Vec<_>>());
+    let vertex_colors = reader
+        .read_colors(0)
+        .map(|c| c.into_rgb_f32().map(Vec3::from_array).collect::<Vec<_>>());
+
+    let material = primitive.material();
+    let pbr = material.pbr_metallic_roughness();
+    let factor = Vec4::from_array(pbr.base_color_factor()).truncate();
+    let texture = pbr.base_color_texture().and_then(|info| {
+        Some((
+            textures.get(info.texture().source().index())?,
+            info.tex_coord(),
+        ))
+    });
+    let uvs = reader
+        .read_tex_coords(texture.map(|(_, set)| set).unwrap_or(0))
+        .map(|uv| uv.into_f32().map(Vec2::from_array).collect::<Vec<_>>());
+
+    let indices = reader
+        .read_indices()
+        .map(|i| i.into_u32().map(|i| i as usize).collect::<Vec<_>>())
+        .unwrap_or_else(|| (0..positions.len()).collect());
+    let faces: Vec<[usize; 3]> = match primitive.mode() {
+        Mode::Triangles => indices
+            .chunks_exact(3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect(),
+        Mode::TriangleStrip => (0..indices.len().saturating_sub(2))
+            .map(|i| match i % 2 {
+                0 => [indices[i], indices[i + 1], indices[i + 2]],
+                _ => [indices[i + 1], indices[i], indices[i + 2]],
+            })
+            .collect(),
+        Mode::TriangleFan => (1..indices.len().saturating_sub(1))
+            .map(|i| [indices[0], indices[i], indices[i + 1]])
+            .collect(),
+        _ => Vec::new(),
+    };
+    if let Some(i) = faces.iter().flatten().find(|i| **i >= positions.len()) {
+        return Err(report(&format!(
+            "index {i} is out of range of {} vertices",
+            positions.len()
+        )));
+    }
+
+    // Colors are combined in linear space, which is what glTF factors and vertex colors use,
+    // and converted back to sRGB for the terminal.
+    let vertex_color = |i: usize| {
+        let mut c = factor;
+        if let Some(colors) = vertex_colors.as_ref() {
+            c *= colors.get(i).copied().unwrap_or(Vec3::ONE);
+        }
+        if let (Some((texture, _)), Some(uvs)) = (texture, uvs.as_ref()) {
+            let s = texture.sample(uvs.get(i).copied().unwrap_or(Vec2::ZERO));
+            c *= (vec3(s.0 as f32, s.1 as f32, s.2 as f32) / 255.0).powf(2.2);
+        }
+        let c = (c.powf(1.0 / 2.2) * 255.0).round();
+        RgbColor(c.x as u8, c.y as u8, c.z as u8)
+    };
+    let varying_color = vertex_colors.is_some() || (texture.is_some() && uvs.is_some());
+
+    let triangles = faces
+        .into_iter()
+        .map(|[a, b, c]| {
+            // glTF faces are counter-clockwise, so the last two vertices are swapped.
+            let idx = [a, c, b];
+            let colors = idx.map(vertex_color);
+            Triangle {
+                v: idx.map(|i| positions[i]),
+                color: match varying_color {
+                    true => average_color(colors),
+                    false => colors[0],
+                },
+                normals: normals
+                    .as_ref()
+                    .and_then(|n| {
+                        idx.iter()
+                            .map(|i| n.get(*i).copied())
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .map(|n| [n[0], n[1], n[2]]),
+                colors: varying_color.then_some(colors),
+                uvs: uvs
+                    .as_ref()
+                    .and_then(|uv| {
+                        idx.iter()
+                            .map(|i| uv.get(*i).copied())
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .map(|uv| [uv[0], uv[1], uv[2]]),
+            }
+        })
+        .collect();
+
+    Ok(GltfPrimitive {
+        mesh: triangles,
+        material: material.index(),
+    })
+}
+
+fn texture_from_image(image: &::gltf::image::Data) -> Texture {
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| -> u8 {
+        match bytes_per_channel {
+            1 => bytes[0],
+            2 => (u16::from_le_bytes([bytes[0], bytes[1]]) >> 8) as u8,
+            _ => {
+                let f = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (f.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        }
+    };
+    let pixels = image
+        .pixels
+        .chunks_exact(channels * bytes_per_channel)
+        .map(|pixel| {
+            let c = |i: usize| channel(&pixel[i * bytes_per_channel..]);
+            match channels {
+                // Grayscale and two channel images only carry luminance in the first channel
+                1 | 2 => RgbColor(c(0), c(0), c(0)),
+                _ => RgbColor(c(0), c(1), c(2)),
+            }
+        })
+        .collect();
+    Texture {
+        dimensions: uvec2(image.width, image.height),
+        pixels,
+    }
+}
@@ -3,16 +3,39 @@ pub(crate) use super::*;
 use crate::prelude::*;
 pub(crate) use tobj::{load_obj, Material, Mesh};
 
+pub mod gltf;
+pub mod ply;
+pub mod primitives;
+pub mod processing;
+pub mod raycast;
+pub mod simplify;
+pub mod stl;
+
 #[derive(Debug, Error)]
 pub enum ResourceError {
     #[error("failed to load obj file")]
     ObjLoadError,
+    #[error("failed to load stl file")]
+    StlLoadError,
+    #[error("failed to write stl file")]
+    StlWriteError,
+    #[error("failed to load ply file")]
+    PlyLoadError,
+    #[error("failed to load gltf file")]
+    GltfLoadError,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Triangle {
     pub v: [Vec3A; 3],
     pub color: RgbColor,
+    /// Per vertex normals, when the source provides them. Flat shaded formats such as STL store
+    /// the face normal for each vertex.
+    pub normals: Option<[Vec3A; 3]>,
+    /// Per vertex colors, interpolated across the triangle by the rasterizer in place of `color`.
+    pub colors: Option<[RgbColor; 3]>,
+    /// Per vertex texture coordinates.
+    pub uvs: Option<[Vec2; 3]>,
 }
 
 #[derive(Debug, Clone)]
@@ -20,12 +43,99 @@ pub struct SimpleMesh {
     pub triangles: Rc<[Triangle]>,
 }
 
+/// An RGB image, stored row by row from the top left corner.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub dimensions: UVec2,
+    pub pixels: Vec<RgbColor>,
+}
+
+impl std::default::Default for Triangle {
+    fn default() -> Self {
+        Self {
+            v: [Vec3A::ZERO; 3],
+            color: RgbColor(255, 255, 255),
+            normals: None,
+            colors: None,
+            uvs: None,
+        }
+    }
+}
+
+impl FromIterator<Triangle> for SimpleMesh {
+    fn from_iter<T: IntoIterator<Item = Triangle>>(iter: T) -> Self {
+        Self {
+            triangles: Rc::from_iter(iter),
+        }
+    }
+}
+
 impl Triangle {
     pub fn normal(&self) -> Vec3A {
         (self.v[2] - self.v[0])
             .cross(self.v[1] - self.v[0])
             .normalize()
     }
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        //! Applies `transform` to the vertices and normals. Mirroring transforms would turn the
+        //! triangle inside out, so the winding is reversed for those.
+        self.transformed_with(
+            transform,
+            transform.inverse().transpose(),
+            transform.determinant() < 0.0,
+        )
+    }
+    /// [`Triangle::transformed`] with the normal tensor and mirroring precomputed, for
+    /// transforming many triangles by the same matrix.
+    pub(crate) fn transformed_with(
+        &self,
+        transform: Mat4,
+        normal_tensor: Mat4,
+        mirrored: bool,
+    ) -> Self {
+        let mut triangle = Self {
+            v: self.v.map(|v| transform.transform_point3a(v)),
+            normals: self
+                .normals
+                .map(|n| n.map(|n| normal_tensor.transform_vector3a(n).normalize_or_zero())),
+            ..*self
+        };
+        if mirrored {
+            triangle.swap_last_vertices();
+        }
+        triangle
+    }
+    /// Swaps the second and third vertex along with their attributes, reversing the winding.
+    pub(crate) fn swap_last_vertices(&mut self) {
+        self.v.swap(1, 2);
+        if let Some(n) = self.normals.as_mut() {
+            n.swap(1, 2);
+        }
+        if let Some(c) = self.colors.as_mut() {
+            c.swap(1, 2);
+        }
+        if let Some(uv) = self.uvs.as_mut() {
+            uv.swap(1, 2);
+        }
+    }
+}
+
+impl Texture {
+    pub fn sample(&self, uv: Vec2) -> RgbColor {
+        //! Nearest neighbour lookup, coordinates outside `0.0..1.0` wrap around.
+        if self.pixels.is_empty() {
+            return RgbColor(255, 255, 255);
+        }
+        let uv = uv - uv.floor();
+        let x = ((uv.x * self.dimensions.x as f32) as u32).min(self.dimensions.x - 1);
+        let y = ((uv.y * self.dimensions.y as f32) as u32).min(self.dimensions.y - 1);
+        self.pixels[(y * self.dimensions.x + x) as usize]
+    }
+}
+
+pub(crate) fn average_color(colors: [RgbColor; 3]) -> RgbColor {
+    let avg = |f: fn(&RgbColor) -> u8| (colors.iter().map(|c| f(c) as u16).sum::<u16>() / 3) as u8;
+    RgbColor(avg(|c| c.0), avg(|c| c.1), avg(|c| c.2))
 }
 
 impl From<(Mesh, &[Material])> for SimpleMesh {
@@ -33,7 +143,8 @@ impl From<(Mesh, &[Material])> for SimpleMesh {
         Self {
             triangles: Rc::from_iter((0..value.0.indices.len() / 3).map(|i| i * 3).map(|i| {
                 let get_vertex = |triangle_index: usize| {
-                    let v1_i = value.0.indices[triangle_index] as usize;
+                    // positions are flattened xyz triples
+                    let v1_i = 3 * value.0.indices[triangle_index] as usize;
                     vec3a(
                         value.0.positions[v1_i],
                         value.0.positions[v1_i + 1],
@@ -52,7 +163,11 @@ impl From<(Mesh, &[Material])> for SimpleMesh {
                         }
                         None => RgbColor(255, 255, 255),
                     },
-                    v: [get_vertex(i), get_vertex(i + 1), get_vertex(i + 2)],
+                    // obj faces are counter-clockwise, Triangle::normal expects clockwise
+                    v: [get_vertex(i), get_vertex(i + 2), get_vertex(i + 1)],
+                    normals: None,
+                    colors: None,
+                    uvs: None,
                 }
             })),
         }
@@ -61,10 +176,31 @@ impl From<(Mesh, &[Material])> for SimpleMesh {
 
 impl From<Mesh> for SimpleMesh {
     fn from(value: Mesh) -> Self {
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tetrahedron.obj");
+        //! let options = tobj::LoadOptions { triangulate: true, ..Default::default() };
+        //! let (models, _) = tobj::load_obj(path, &options).unwrap();
+        //! let mesh = models[0].mesh.clone();
+        //! for simple in [SimpleMesh::from(mesh.clone()), SimpleMesh::from((mesh, &[][..]))] {
+        //!     assert_eq!(simple.triangles.len(), 4);
+        //!     // The first face is `f 1 3 2`, each vertex read from its own position
+        //!     let first = simple.triangles[0].v;
+        //!     assert!(first.contains(&vec3a(0.0, 0.0, 0.0)));
+        //!     assert!(first.contains(&vec3a(1.0, 0.0, 0.0)));
+        //!     assert!(first.contains(&vec3a(0.0, 1.0, 0.0)));
+        //!     // Counter-clockwise obj faces give outward normals
+        //!     let center = Vec3A::splat(0.25);
+        //!     for triangle in simple.triangles.iter() {
+        //!         assert!(triangle.normal().dot(triangle.v[0] - center) > 0.0);
+        //!     }
+        //! }
+        //! ```
         Self {
             triangles: Rc::from_iter((0..value.indices.len() / 3).map(|i| i * 3).map(|i| {
                 let get_vertex = |triangle_index: usize| {
-                    let v1_i = value.indices[triangle_index] as usize;
+                    // positions are flattened xyz triples
+                    let v1_i = 3 * value.indices[triangle_index] as usize;
                     vec3a(
                         value.positions[v1_i],
                         value.positions[v1_i + 1],
@@ -73,7 +209,11 @@ impl From<Mesh> for SimpleMesh {
                 };
                 Triangle {
                     color: RgbColor(255, 255, 255),
-                    v: [get_vertex(i), get_vertex(i + 1), get_vertex(i + 2)],
+                    // obj faces are counter-clockwise, Triangle::normal expects clockwise
+                    v: [get_vertex(i), get_vertex(i + 2), get_vertex(i + 1)],
+                    normals: None,
+                    colors: None,
+                    uvs: None,
                 }
             })),
         }
@@ -0,0 +1,360 @@
+pub(crate) use super::*;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar {
+        name: String,
+        ty: ScalarType,
+    },
+    List {
+        name: String,
+        count_ty: ScalarType,
+        item_ty: ScalarType,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads scalars out of the body of a ply file, regardless of its encoding.
+enum BodyReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl SimpleMesh {
+    pub fn load_ply(path: impl AsRef<Path>) -> Result<Self, ResourceError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .change_context_lazy(|| ResourceError::PlyLoadError)
+            .attach_printable_lazy(|| format!("could not read {}", path.display()))?;
+        Self::from_ply_bytes(&bytes)
+    }
+    pub fn from_ply_bytes(bytes: &[u8]) -> Result<Self, ResourceError> {
+        //! Parses ascii, binary little endian and binary big endian ply files. Faces are fanned
+        //! into triangles and reordered to the winding [`Triangle::normal`] expects.
+        //! `red`/`green`/`blue` vertex properties become per vertex [`Triangle::colors`], with
+        //! [`Triangle::color`] set to their average. `nx`/`ny`/`nz` become per vertex normals and
+        //! `u`/`v` or `s`/`t` become texture coordinates.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let ply = "ply
+        //! format ascii 1.0
+        //! element vertex 3
+        //! property float x
+        //! property float y
+        //! property float z
+        //! property uchar red
+        //! property uchar green
+        //! property uchar blue
+        //! element face 1
+        //! property list uchar int vertex_indices
+        //! end_header
+        //! 0 0 0 255 0 0
+        //! 1 0 0 0 255 0
+        //! 0 1 0 0 0 255
+        //! 3 0 1 2
+        //! ";
+        //! let mesh = SimpleMesh::from_ply_bytes(ply.as_bytes()).unwrap();
+        //! let triangle = mesh.triangles[0];
+        //! assert_eq!(triangle.normal(), vec3a(0.0, 0.0, 1.0));
+        //! assert_eq!(triangle.colors.unwrap()[0], RgbColor(255, 0, 0));
+        //! ```
+        let report = |msg: String| Report::new(ResourceError::PlyLoadError).attach_printable(msg);
+
+        let header_end = bytes
+            .windows(b"end_header".len())
+            .position(|w| w == b"end_header")
+            .ok_or_else(|| report("missing end_header".to_owned()))?;
+        let body_start = bytes[header_end..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|i| header_end + i + 1)
+            .unwrap_or(bytes.len());
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .change_context_lazy(|| ResourceError::PlyLoadError)
+            .attach_printable_lazy(|| "header is not valid utf8")?;
+        let (format, elements) = parse_header(header)?;
+
+        let mut reader = match format {
+            PlyFormat::Ascii => BodyReader::Ascii(
+                std::str::from_utf8(&bytes[body_start..])
+                    .change_context_lazy(|| ResourceError::PlyLoadError)
+                    .attach_printable_lazy(|| "ascii body is not valid utf8")?
+                    .split_ascii_whitespace(),
+            ),
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => BodyReader::Binary {
+                bytes: &bytes[body_start..],
+                big_endian: format == PlyFormat::BinaryBigEndian,
+            },
+        };
+
+        let mut positions: Vec<Vec3A> = Vec::new();
+        let mut normals: Vec<Vec3A> = Vec::new();
+        let mut colors: Vec<RgbColor> = Vec::new();
+        let mut uvs: Vec<Vec2> = Vec::new();
+        let mut faces: Vec<Vec<usize>> = Vec::new();
+
+        for element in elements.iter() {
+            let index_of = |name: &str| {
+                element.properties.iter().position(|p| match p {
+                    Property::Scalar { name: n, .. } => n == name,
+                    Property::List { .. } => false,
+                })
+            };
+            let position_idx = ["x", "y", "z"].map(index_of);
+            let normal_idx = ["nx", "ny", "nz"].map(index_of);
+            let color_idx = ["red", "green", "blue"].map(index_of);
+            // Exporters disagree on what to call texture coordinates
+            let uv_idx = [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]]
+                .into_iter()
+                .map(|names| names.map(index_of))
+                .find(|idx| idx.iter().all(Option::is_some));
+            let has = |idx: &[Option<usize>; 3]| idx.iter().all(Option::is_some);
+
+            let mut scalars = vec![0.0f64; element.properties.len()];
+            for _ in 0..element.count {
+                let mut list = None;
+                for (value, property) in scalars.iter_mut().zip(element.properties.iter()) {
+                    match property {
+                        Property::Scalar { ty, .. } => *value = reader.read(*ty)?,
+                        Property::List {
+                            name,
+                            count_ty,
+                            item_ty,
+                        } => {
+                            let len = reader.read(*count_ty)? as usize;
+                            let items = (0..len)
+                                .map(|_| reader.read(*item_ty).map(|v| v as usize))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            if name == "vertex_indices" || name == "vertex_index" {
+                                list = Some(items);
+                            }
+                        }
+                    }
+                }
+
+                match element.name.as_str() {
+                    "vertex" => {
+                        let get = |idx: [Option<usize>; 3]| {
+                            Vec3A::from_array(idx.map(|i| scalars[i.unwrap()] as f32))
+                        };
+                        if !has(&position_idx) {
+                            return Err(report("vertex element is missing x, y or z".to_owned()));
+                        }
+                        positions.push(get(position_idx));
+                        if has(&normal_idx) {
+                            normals.push(get(normal_idx));
+                        }
+                        if let Some([u, v]) = uv_idx {
+                            uvs.push(vec2(scalars[u.unwrap()] as f32, scalars[v.unwrap()] as f32));
+                        }
+                        if has(&color_idx) {
+                            let channel = |i: Option<usize>| {
+                                let i = i.unwrap();
+                                let ty = match element.properties[i] {
+                                    Property::Scalar { ty, .. } => ty,
+                                    Property::List { .. } => unreachable!(),
+                                };
+                                color_channel(scalars[i], ty)
+                            };
+                            colors.push(RgbColor(
+                                channel(color_idx[0]),
+                                channel(color_idx[1]),
+                                channel(color_idx[2]),
+                            ));
+                        }
+                    }
+                    "face" => {
+                        faces.push(list.ok_or_else(|| {
+                            report("face element has no vertex_indices".to_owned())
+                        })?)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut triangles = Vec::with_capacity(faces.len());
+        for face in faces.iter().filter(|f| f.len() >= 3) {
+            if let Some(i) = face.iter().find(|i| **i >= positions.len()) {
+                return Err(report(format!(
+                    "face references vertex {i} but there are only {} vertices",
+                    positions.len()
+                )));
+            }
+            // ply faces are counter-clockwise, so the last two vertices of each triangle are
+            // swapped.
+            for j in 1..face.len() - 1 {
+                let idx = [face[0], face[j + 1], face[j]];
+                let vertex_colors = (!colors.is_empty()).then(|| idx.map(|i| colors[i]));
+                triangles.push(Triangle {
+                    v: idx.map(|i| positions[i]),
+                    color: vertex_colors
+                        .map(average_color)
+                        .unwrap_or(RgbColor(255, 255, 255)),
+                    normals: (!normals.is_empty())
+                        .then(|| idx.map(|i| normals[i].normalize_or_zero())),
+                    colors: vertex_colors,
+                    uvs: (!uvs.is_empty()).then(|| idx.map(|i| uvs[i])),
+                });
+            }
+        }
+        Ok(triangles.into_iter().collect())
+    }
+}
+
+fn parse_header(header: &str) -> Result<(PlyFormat, Vec<Element>), ResourceError> {
+    let report = |line: usize, msg: &str| {
+        Report::new(ResourceError::PlyLoadError)
+            .attach_printable(format!("line {}: {msg}", line + 1))
+    };
+    let mut lines = header.lines().enumerate();
+    if lines.next().map(|(_, l)| l.trim()) != Some("ply") {
+        return Err(report(0, "file does not start with \"ply\""));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (i, line) in lines {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["format", f, _version] => {
+                format = Some(match *f {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    other => return Err(report(i, &format!("unknown format {other}"))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| report(i, &format!("{count} is not a valid element count")))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| report(i, "property declared before any element"))?
+                .properties
+                .push(Property::List {
+                    name: name.to_string(),
+                    count_ty: scalar_type(count_ty).ok_or_else(|| report(i, "unknown type"))?,
+                    item_ty: scalar_type(item_ty).ok_or_else(|| report(i, "unknown type"))?,
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| report(i, "property declared before any element"))?
+                .properties
+                .push(Property::Scalar {
+                    name: name.to_string(),
+                    ty: scalar_type(ty).ok_or_else(|| report(i, "unknown type"))?,
+                }),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => {
+                return Err(report(
+                    i,
+                    &format!("unexpected header line \"{}\"", line.trim()),
+                ))
+            }
+        }
+    }
+    Ok((
+        format.ok_or_else(|| report(0, "header has no format line"))?,
+        elements,
+    ))
+}
+
+fn scalar_type(name: &str) -> Option<ScalarType> {
+    Some(match name {
+        "char" | "int8" => ScalarType::I8,
+        "uchar" | "uint8" => ScalarType::U8,
+        "short" | "int16" => ScalarType::I16,
+        "ushort" | "uint16" => ScalarType::U16,
+        "int" | "int32" => ScalarType::I32,
+        "uint" | "uint32" => ScalarType::U32,
+        "float" | "float32" => ScalarType::F32,
+        "double" | "float64" => ScalarType::F64,
+        _ => return None,
+    })
+}
+
+/// Integer color channels span their type's range, float channels span `0.0..=1.0`.
+fn color_channel(value: f64, ty: ScalarType) -> u8 {
+    let max = match ty {
+        ScalarType::U8 | ScalarType::I8 => 255.0,
+        ScalarType::U16 | ScalarType::I16 => 65535.0,
+        ScalarType::U32 | ScalarType::I32 => u32::MAX as f64,
+        ScalarType::F32 | ScalarType::F64 => 1.0,
+    };
+    (value / max * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+impl<'a> BodyReader<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, ResourceError> {
+        let report = || Report::new(ResourceError::PlyLoadError);
+        match self {
+            BodyReader::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| report().attach_printable("unexpected end of ascii body"))?;
+                word.parse::<f64>()
+                    .map_err(|_| report().attach_printable(format!("{word} is not a number")))
+            }
+            BodyReader::Binary { bytes, big_endian } => {
+                let len = match ty {
+                    ScalarType::I8 | ScalarType::U8 => 1,
+                    ScalarType::I16 | ScalarType::U16 => 2,
+                    ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+                    ScalarType::F64 => 8,
+                };
+                if bytes.len() < len {
+                    return Err(report().attach_printable("unexpected end of binary body"));
+                }
+                let (value, rest) = bytes.split_at(len);
+                *bytes = rest;
+
+                let mut buf = [0u8; 8];
+                buf[..len].copy_from_slice(value);
+                if *big_endian {
+                    buf[..len].reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buf[0] as i8 as f64,
+                    ScalarType::U8 => buf[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    ScalarType::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    ScalarType::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Procedurally generated meshes. Every generator has a builder on [`SimpleMesh`] with sensible
+//! defaults, e.g. `SimpleMesh::uv_sphere_builder().radius(2.0).segments(24).build()`.
+//! Meshes are centered on the origin, face outwards with the winding [`Triangle::normal`]
+//! expects, and come with smooth (or per face for flat sides) normals and texture coordinates.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! let sphere = SimpleMesh::icosphere_builder().subdivisions(1).build();
+//! assert_eq!(sphere.triangles.len(), 80);
+//! assert!(sphere.triangles.iter().all(|t| t.normal().dot(t.v[0]) > 0.0));
+//! ```
+pub(crate) use super::*;
+use std::f32::consts::{PI, TAU};
+
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    position: Vec3A,
+    normal: Vec3A,
+    uv: Vec2,
+}
+
+/// Collects triangles, fixing their winding up against the vertex normals as they are added.
+struct MeshWriter {
+    triangles: Vec<Triangle>,
+    color: RgbColor,
+}
+
+#[buildstructor]
+impl SimpleMesh {
+    #[builder(visibility = "pub")]
+    pub fn cube_new(
+        size: Option<f32>,
+        subdivisions: Option<u32>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! An axis aligned cube with `subdivisions` quads along each edge of every face.
+        let half = size.unwrap_or(1.0) / 2.0;
+        let n = subdivisions.unwrap_or(1).max(1);
+        let mut writer = MeshWriter::new(color);
+        for axis in [Vec3A::X, Vec3A::Y, Vec3A::Z] {
+            for normal in [axis, -axis] {
+                let (u, v) = normal.any_orthonormal_pair();
+                writer.grid(n, n, |i, j| {
+                    let uv = vec2(i as f32 / n as f32, j as f32 / n as f32);
+                    Vertex {
+                        position: (normal + u * (uv.x * 2.0 - 1.0) + v * (uv.y * 2.0 - 1.0)) * half,
+                        normal,
+                        uv,
+                    }
+                });
+            }
+        }
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn plane_new(
+        size: Option<Vec2>,
+        subdivisions: Option<UVec2>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A grid in the XZ plane facing up the Y axis, `size` spans X and Z.
+        let size = size.unwrap_or(Vec2::ONE);
+        let n = subdivisions.unwrap_or(UVec2::ONE).max(UVec2::ONE);
+        let mut writer = MeshWriter::new(color);
+        writer.grid(n.x, n.y, |i, j| {
+            let uv = vec2(i as f32 / n.x as f32, j as f32 / n.y as f32);
+            Vertex {
+                position: vec3a((uv.x - 0.5) * size.x, 0.0, (uv.y - 0.5) * size.y),
+                normal: Vec3A::Y,
+                uv,
+            }
+        });
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn uv_sphere_new(
+        radius: Option<f32>,
+        segments: Option<u32>,
+        rings: Option<u32>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A sphere made of `segments` slices around the Y axis and `rings` bands from pole to
+        //! pole.
+        let radius = radius.unwrap_or(1.0);
+        let segments = segments.unwrap_or(16).max(3);
+        let rings = rings.unwrap_or(8).max(2);
+        let mut writer = MeshWriter::new(color);
+        writer.grid(segments, rings, |i, j| {
+            let uv = vec2(i as f32 / segments as f32, j as f32 / rings as f32);
+            let normal = sphere_point(uv.x * TAU, uv.y * PI);
+            Vertex {
+                position: normal * radius,
+                normal,
+                uv,
+            }
+        });
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn icosphere_new(
+        radius: Option<f32>,
+        subdivisions: Option<u32>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A subdivided icosahedron, every subdivision splits each face into four.
+        let radius = radius.unwrap_or(1.0);
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let corners = [
+            vec3a(-1.0, t, 0.0),
+            vec3a(1.0, t, 0.0),
+            vec3a(-1.0, -t, 0.0),
+            vec3a(1.0, -t, 0.0),
+            vec3a(0.0, -1.0, t),
+            vec3a(0.0, 1.0, t),
+            vec3a(0.0, -1.0, -t),
+            vec3a(0.0, 1.0, -t),
+            vec3a(t, 0.0, -1.0),
+            vec3a(t, 0.0, 1.0),
+            vec3a(-t, 0.0, -1.0),
+            vec3a(-t, 0.0, 1.0),
+        ]
+        .map(Vec3A::normalize);
+        let mut faces = [
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ]
+        .map(|f| f.map(|i| corners[i]))
+        .to_vec();
+        for _ in 0..subdivisions.unwrap_or(2) {
+            faces = faces
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let ab = (a + b).normalize();
+                    let bc = (b + c).normalize();
+                    let ca = (c + a).normalize();
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        let mut writer = MeshWriter::new(color);
+        for face in faces {
+            let mut uvs = face.map(|n| vec2(0.5 + n.z.atan2(n.x) / TAU, n.y.acos() / PI));
+            // Faces straddling the seam would otherwise wrap the whole texture across them
+            let max_u = uvs.iter().fold(0.0f32, |m, uv| m.max(uv.x));
+            uvs.iter_mut()
+                .filter(|uv| max_u - uv.x > 0.5)
+                .for_each(|uv| uv.x += 1.0);
+            let vertices = [0, 1, 2].map(|i| Vertex {
+                position: face[i] * radius,
+                normal: face[i],
+                uv: uvs[i],
+            });
+            writer.triangle(vertices);
+        }
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn cylinder_new(
+        radius: Option<f32>,
+        height: Option<f32>,
+        segments: Option<u32>,
+        rings: Option<u32>,
+        caps: Option<bool>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A cylinder along the Y axis, `rings` bands tall, optionally closed with flat caps.
+        let radius = radius.unwrap_or(0.5);
+        let height = height.unwrap_or(1.0);
+        let segments = segments.unwrap_or(16).max(3);
+        let rings = rings.unwrap_or(1).max(1);
+        let mut writer = MeshWriter::new(color);
+        writer.grid(segments, rings, |i, j| {
+            let uv = vec2(i as f32 / segments as f32, j as f32 / rings as f32);
+            let normal = ring_point(uv.x * TAU);
+            Vertex {
+                position: normal * radius + Vec3A::Y * (0.5 - uv.y) * height,
+                normal,
+                uv,
+            }
+        });
+        if caps.unwrap_or(true) {
+            writer.disc(segments, radius, Vec3A::Y * height / 2.0, Vec3A::Y);
+            writer.disc(segments, radius, -Vec3A::Y * height / 2.0, -Vec3A::Y);
+        }
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn cone_new(
+        radius: Option<f32>,
+        height: Option<f32>,
+        segments: Option<u32>,
+        rings: Option<u32>,
+        cap: Option<bool>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A cone along the Y axis with its tip at `height / 2` and base at `-height / 2`.
+        let radius = radius.unwrap_or(0.5);
+        let height = height.unwrap_or(1.0);
+        let segments = segments.unwrap_or(16).max(3);
+        let rings = rings.unwrap_or(1).max(1);
+        let slope = radius / height;
+        let mut writer = MeshWriter::new(color);
+        writer.grid(segments, rings, |i, j| {
+            let uv = vec2(i as f32 / segments as f32, j as f32 / rings as f32);
+            let around = ring_point(uv.x * TAU);
+            Vertex {
+                position: around * radius * uv.y + Vec3A::Y * (0.5 - uv.y) * height,
+                normal: (around + Vec3A::Y * slope).normalize(),
+                uv,
+            }
+        });
+        if cap.unwrap_or(true) {
+            writer.disc(segments, radius, -Vec3A::Y * height / 2.0, -Vec3A::Y);
+        }
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn torus_new(
+        major_radius: Option<f32>,
+        minor_radius: Option<f32>,
+        major_segments: Option<u32>,
+        minor_segments: Option<u32>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A torus around the Y axis. `major_radius` is the distance from the center to the
+        //! middle of the tube, `minor_radius` the radius of the tube.
+        let major_radius = major_radius.unwrap_or(0.75);
+        let minor_radius = minor_radius.unwrap_or(0.25);
+        let major_segments = major_segments.unwrap_or(24).max(3);
+        let minor_segments = minor_segments.unwrap_or(12).max(3);
+        let mut writer = MeshWriter::new(color);
+        writer.grid(major_segments, minor_segments, |i, j| {
+            let uv = vec2(
+                i as f32 / major_segments as f32,
+                j as f32 / minor_segments as f32,
+            );
+            let around = ring_point(uv.x * TAU);
+            let (sin, cos) = (uv.y * TAU).sin_cos();
+            let normal = around * cos + Vec3A::Y * sin;
+            Vertex {
+                position: around * major_radius + normal * minor_radius,
+                normal,
+                uv,
+            }
+        });
+        writer.finish()
+    }
+    #[builder(visibility = "pub")]
+    pub fn capsule_new(
+        radius: Option<f32>,
+        height: Option<f32>,
+        segments: Option<u32>,
+        rings: Option<u32>,
+        color: Option<RgbColor>,
+    ) -> SimpleMesh {
+        //! A cylinder along the Y axis capped with hemispheres. `height` is the length of the
+        //! cylindrical section and `rings` the number of bands in each hemisphere.
+        let radius = radius.unwrap_or(0.5);
+        let height = height.unwrap_or(1.0);
+        let segments = segments.unwrap_or(16).max(3);
+        let rings = rings.unwrap_or(4).max(1);
+        // One extra row duplicates the equator of each hemisphere to form the straight section
+        let total_rows = rings * 2 + 1;
+        let mut writer = MeshWriter::new(color);
+        writer.grid(segments, total_rows, |i, j| {
+            let (polar, offset) = match j {
+                j if j <= rings => (j as f32 / rings as f32 * PI / 2.0, height / 2.0),
+                j => ((j - 1) as f32 / rings as f32 * PI / 2.0, -height / 2.0),
+            };
+            let u = i as f32 / segments as f32;
+            let normal = sphere_point(u * TAU, polar);
+            let position = normal * radius + Vec3A::Y * offset;
+            Vertex {
+                position,
+                normal,
+                uv: vec2(
+                    u,
+                    (height / 2.0 + radius - position.y) / (height + 2.0 * radius),
+                ),
+            }
+        });
+        writer.finish()
+    }
+}
+
+/// A point on the unit sphere, `polar` is measured from the positive Y axis.
+fn sphere_point(azimuth: f32, polar: f32) -> Vec3A {
+    let (sin, cos) = polar.sin_cos();
+    ring_point(azimuth) * sin + Vec3A::Y * cos
+}
+
+/// A point on the unit circle in the XZ plane.
+fn ring_point(azimuth: f32) -> Vec3A {
+    let (sin, cos) = azimuth.sin_cos();
+    vec3a(cos, 0.0, sin)
+}
+
+impl MeshWriter {
+    fn new(color: Option<RgbColor>) -> Self {
+        Self {
+            triangles: Vec::new(),
+            color: color.unwrap_or(RgbColor(255, 255, 255)),
+        }
+    }
+    fn triangle(&mut self, mut v: [Vertex; 3]) {
+        let mut triangle = Triangle {
+            v: v.map(|v| v.position),
+            color: self.color,
+            ..Default::default()
+        };
+        let normal = triangle.normal();
+        // Collapsed triangles at poles and cone tips cover no cells, so they are dropped
+        if !normal.is_finite() {
+            return;
+        }
+        if normal.dot(v[0].normal + v[1].normal + v[2].normal) < 0.0 {
+            v.swap(1, 2);
+            triangle.v.swap(1, 2);
+        }
+        triangle.normals = Some(v.map(|v| v.normal));
+        triangle.uvs = Some(v.map(|v| v.uv));
+        self.triangles.push(triangle);
+    }
+    fn grid(&mut self, columns: u32, rows: u32, vertex: impl Fn(u32, u32) -> Vertex) {
+        //! Two triangles for each cell of a `columns` by `rows` grid of vertices.
+        for j in 0..rows {
+            for i in 0..columns {
+                let corners =
+                    [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)].map(|(i, j)| vertex(i, j));
+                self.triangle([corners[0], corners[1], corners[2]]);
+                self.triangle([corners[0], corners[2], corners[3]]);
+            }
+        }
+    }
+    fn disc(&mut self, segments: u32, radius: f32, center: Vec3A, normal: Vec3A) {
+        let vertex = |i: u32| {
+            let around = ring_point(i as f32 / segments as f32 * TAU);
+            Vertex {
+                position: center + around * radius,
+                normal,
+                uv: vec2(around.x, around.z) * 0.5 + 0.5,
+            }
+        };
+        let middle = Vertex {
+            position: center,
+            normal,
+            uv: Vec2::splat(0.5),
+        };
+        for i in 0..segments {
+            self.triangle([middle, vertex(i), vertex(i + 1)]);
+        }
+    }
+    fn finish(self) -> SimpleMesh {
+        self.triangles.into_iter().collect()
+    }
+}
@@ -0,0 +1,304 @@
+//! Whole mesh operations. Meshes are immutable once built, so every operation returns a new
+//! [`SimpleMesh`].
+pub(crate) use super::*;
+use std::collections::HashMap;
+
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3A,
+    pub max: Vec3A,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3A,
+    pub radius: f32,
+}
+
+impl Aabb {
+    pub fn from_points(points: impl IntoIterator<Item = Vec3A>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, p| Self {
+                min: aabb.min.min(p),
+                max: aabb.max.max(p),
+            },
+        ))
+    }
+    pub fn center(&self) -> Vec3A {
+        (self.min + self.max) / 2.0
+    }
+    pub fn size(&self) -> Vec3A {
+        self.max - self.min
+    }
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        //! The box enclosing this one after `transform`, which is usually larger than the
+        //! transformed mesh's own box.
+        let corners = (0..8).map(|i| {
+            let pick = |bit: u32, min: f32, max: f32| if i & bit == 0 { min } else { max };
+            transform.transform_point3a(vec3a(
+                pick(1, self.min.x, self.max.x),
+                pick(2, self.min.y, self.max.y),
+                pick(4, self.min.z, self.max.z),
+            ))
+        });
+        Self::from_points(corners).unwrap()
+    }
+}
+
+impl BoundingSphere {
+    pub fn from_points(points: &[Vec3A]) -> Option<Self> {
+        //! Ritter's bounding sphere, within a few percent of the smallest enclosing sphere.
+        let first = *points.first()?;
+        let farthest_from = |p: Vec3A| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+                .unwrap()
+        };
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let mut sphere = Self {
+            center: (a + b) / 2.0,
+            radius: a.distance(b) / 2.0,
+        };
+        for p in points {
+            let d = p.distance(sphere.center);
+            if d > sphere.radius {
+                let radius = (sphere.radius + d) / 2.0;
+                sphere.center += (*p - sphere.center) * ((radius - sphere.radius) / d);
+                sphere.radius = radius;
+            }
+        }
+        Some(sphere)
+    }
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        //! Non uniform scales grow the radius by the largest axis scale, so the result still
+        //! encloses the transformed mesh.
+        let scale = [transform.x_axis, transform.y_axis, transform.z_axis]
+            .map(|axis| axis.truncate().length())
+            .into_iter()
+            .fold(0.0, f32::max);
+        Self {
+            center: transform.transform_point3a(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+impl SimpleMesh {
+    pub fn vertices(&self) -> impl Iterator<Item = Vec3A> + '_ {
+        self.triangles.iter().flat_map(|t| t.v)
+    }
+    pub fn transformed(&self, transform: impl Into<Mat4>) -> Self {
+        //! Applies an `Affine3A` or `Mat4` to every triangle, see [`Triangle::transformed`].
+        let transform = transform.into();
+        self.triangles
+            .iter()
+            .map(|t| t.transformed(transform))
+            .collect()
+    }
+    pub fn aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices())
+    }
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_points(&self.vertices().collect::<Vec<_>>())
+    }
+    pub fn recentered(&self) -> Self {
+        //! Moves the mesh so the center of its bounding box is at the origin.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let cube = SimpleMesh::cube_builder()
+        //!     .size(2.0)
+        //!     .build()
+        //!     .transformed(Mat4::from_translation(vec3(3.0, -1.0, 0.5)));
+        //! let aabb = cube.recentered().aabb().unwrap();
+        //! assert!(aabb.center().abs_diff_eq(Vec3A::ZERO, 1e-6));
+        //! assert!(aabb.size().abs_diff_eq(Vec3A::splat(2.0), 1e-6));
+        //! ```
+        match self.aabb() {
+            Some(aabb) => self.transformed(Mat4::from_translation(-Vec3::from(aabb.center()))),
+            None => self.clone(),
+        }
+    }
+    pub fn normalized(&self) -> Self {
+        //! Recenters the mesh and uniformly scales it so the longest side of its bounding box is
+        //! one unit long, which puts arbitrary models in view of a default camera.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let placement =
+        //!     Mat4::from_translation(vec3(5.0, 0.0, 0.0)) * Mat4::from_scale(vec3(4.0, 2.0, 1.0));
+        //! let brick = SimpleMesh::cube_builder().size(1.0).build().transformed(placement);
+        //! let aabb = brick.normalized().aabb().unwrap();
+        //! assert!(aabb.center().abs_diff_eq(Vec3A::ZERO, 1e-6));
+        //! assert!(aabb.size().abs_diff_eq(vec3a(1.0, 0.5, 0.25), 1e-6));
+        //! ```
+        let Some(aabb) = self.aabb() else {
+            return self.clone();
+        };
+        let longest = aabb.size().max_element();
+        let scale = if longest > f32::EPSILON {
+            1.0 / longest
+        } else {
+            1.0
+        };
+        self.transformed(
+            Mat4::from_scale(Vec3::splat(scale))
+                * Mat4::from_translation(-Vec3::from(aabb.center())),
+        )
+    }
+    pub fn flipped_winding(&self) -> Self {
+        //! Turns the mesh inside out, reversing every triangle and its vertex normals.
+        self.triangles
+            .iter()
+            .map(|t| {
+                let mut t = *t;
+                t.swap_last_vertices();
+                t.normals = t.normals.map(|n| n.map(|n| -n));
+                t
+            })
+            .collect()
+    }
+    pub fn welded(&self, epsilon: f32) -> Self {
+        //! Snaps vertices closer than `epsilon` to each other onto a single position, so that
+        //! vertices duplicated by file formats or seams are shared again. Triangles that collapse
+        //! as a result are removed.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let triangle = |v| Triangle { v, ..Triangle::default() };
+        //! let mesh: SimpleMesh = [
+        //!     triangle([vec3a(0.0, 0.0, 0.0), vec3a(1.0, 0.0, 0.0), vec3a(0.0, 1.0, 0.0)]),
+        //!     triangle([vec3a(1.0001, 0.0, 0.0), vec3a(1.0, 1.0, 0.0), vec3a(0.0, 1.0001, 0.0)]),
+        //!     // Small enough to collapse onto a single point
+        //!     triangle([vec3a(0.0, 0.0, 0.0), vec3a(0.0001, 0.0, 0.0), vec3a(0.0, 0.0001, 0.0)]),
+        //! ]
+        //! .into_iter()
+        //! .collect();
+        //! let welded = mesh.welded(0.001);
+        //! assert_eq!(welded.triangles.len(), 2);
+        //! assert_eq!(welded.triangles[1].v[0], vec3a(1.0, 0.0, 0.0));
+        //! assert_eq!(welded.triangles[1].v[2], vec3a(0.0, 1.0, 0.0));
+        //! ```
+        let epsilon = epsilon.max(f32::MIN_POSITIVE);
+        let cell = |p: Vec3A| (p / epsilon).floor().as_ivec3();
+        let mut grid: HashMap<IVec3, Vec<Vec3A>> = HashMap::new();
+        let mut weld = |p: Vec3A| -> Vec3A {
+            let c = cell(p);
+            let neighbours = (-1..=1)
+                .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| ivec3(x, y, z))));
+            for offset in neighbours {
+                if let Some(found) = grid
+                    .get(&(c + offset))
+                    .and_then(|ps| ps.iter().find(|q| q.distance(p) <= epsilon))
+                {
+                    return *found;
+                }
+            }
+            grid.entry(c).or_default().push(p);
+            p
+        };
+        self.triangles
+            .iter()
+            .filter_map(|t| {
+                let v = t.v.map(&mut weld);
+                (v[0] != v[1] && v[1] != v[2] && v[2] != v[0]).then_some(Triangle { v, ..*t })
+            })
+            .collect()
+    }
+    pub fn with_face_normals(&self) -> Self {
+        //! Replaces the vertex normals of every triangle with its face normal, for flat shading.
+        //! Degenerate triangles have no face normal and keep the normals they had.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let triangle = |v| Triangle { v, ..Triangle::default() };
+        //! let mesh: SimpleMesh = [
+        //!     triangle([vec3a(0.0, 0.0, 0.0), vec3a(0.0, 1.0, 0.0), vec3a(1.0, 0.0, 0.0)]),
+        //!     triangle([vec3a(0.0, 0.0, 0.0), vec3a(1.0, 0.0, 0.0), vec3a(2.0, 0.0, 0.0)]),
+        //! ]
+        //! .into_iter()
+        //! .collect();
+        //! let flat = mesh.with_face_normals();
+        //! assert_eq!(flat.triangles[0].normals, Some([vec3a(0.0, 0.0, 1.0); 3]));
+        //! assert_eq!(flat.triangles[1].normals, None);
+        //! ```
+        self.triangles
+            .iter()
+            .map(|t| {
+                let face = (t.v[2] - t.v[0]).cross(t.v[1] - t.v[0]).try_normalize();
+                Triangle {
+                    normals: face.map(|n| [n; 3]).or(t.normals),
+                    ..*t
+                }
+            })
+            .collect()
+    }
+    pub fn with_vertex_normals(&self, crease_angle: Option<f32>) -> Self {
+        //! Recomputes smooth vertex normals by averaging the area weighted normals of every
+        //! triangle sharing the vertex position. Faces meeting at more than `crease_angle`
+        //! radians aren't averaged together, keeping hard edges sharp. Meshes with duplicated
+        //! vertices should be [welded](Self::welded) first.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let cube = SimpleMesh::cube_builder().size(1.0).build();
+        //! // Every edge of a cube is a right angle, so a smaller crease angle keeps the faces flat
+        //! let sharp = cube.with_vertex_normals(Some(0.5));
+        //! for (flat, sharp) in cube.triangles.iter().zip(sharp.triangles.iter()) {
+        //!     let (flat, sharp) = (flat.normals.unwrap(), sharp.normals.unwrap());
+        //!     assert!((0..3).all(|i| flat[i].abs_diff_eq(sharp[i], 1e-6)));
+        //! }
+        //! // Without one, the normals at the corners point out between the faces
+        //! let smooth = cube.with_vertex_normals(None);
+        //! for (flat, smooth) in cube.triangles.iter().zip(smooth.triangles.iter()) {
+        //!     let (faces, normals) = (flat.normals.unwrap(), smooth.normals.unwrap());
+        //!     for i in 0..3 {
+        //!         assert!(normals[i].dot(smooth.v[i]) > 0.0 && normals[i].dot(faces[i]) < 0.99);
+        //!     }
+        //! }
+        //! ```
+        let key = |v: Vec3A| v.to_array().map(f32::to_bits);
+        // Area weighted normal of each triangle, the cross product's length is twice the area
+        let weighted = self
+            .triangles
+            .iter()
+            .map(|t| (t.v[2] - t.v[0]).cross(t.v[1] - t.v[0]))
+            .collect::<Vec<_>>();
+        let mut shared: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (i, t) in self.triangles.iter().enumerate() {
+            for v in t.v {
+                shared.entry(key(v)).or_default().push(i);
+            }
+        }
+        let min_cos = crease_angle.map(f32::cos);
+
+        self.triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let face = weighted[i].normalize_or_zero();
+                let normals = t.v.map(|v| {
+                    shared[&key(v)]
+                        .iter()
+                        .map(|j| weighted[*j])
+                        .filter(|n| match min_cos {
+                            Some(min_cos) => n.normalize_or_zero().dot(face) >= min_cos,
+                            None => true,
+                        })
+                        .sum::<Vec3A>()
+                        .try_normalize()
+                        .unwrap_or(face)
+                });
+                Triangle {
+                    normals: Some(normals),
+                    ..*t
+                }
+            })
+            .collect()
+    }
+}
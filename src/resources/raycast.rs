@@ -0,0 +1,108 @@
+//! Ray intersection queries, for picking what is under a cell with
+//! [`Camera::screen_ray`](crate::rasterizing::Camera::screen_ray).
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::resources::raycast::Ray;
+//! let cube = SimpleMesh::cube_builder().size(1.0).build();
+//! let ray = Ray::new(vec3(0.0, 0.0, -5.0), Vec3::Z);
+//! let hit = cube.ray_intersection(&ray).unwrap();
+//! assert!((hit.distance - 4.5).abs() < 1e-4);
+//! assert!((hit.point(&ray) - vec3(0.0, 0.0, -0.5)).length() < 1e-4);
+//!
+//! // Small triangles are hit just the same
+//! let small = cube.transformed(Mat4::from_scale(Vec3::splat(1e-4)));
+//! let hit = small.ray_intersection(&ray).unwrap();
+//! assert!((hit.distance - 5.0).abs() < 1e-3);
+//! ```
+pub(crate) use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Distances along the ray are measured in multiples of `direction`, which is usually of
+    /// unit length.
+    pub direction: Vec3,
+}
+
+/// The closest triangle of a mesh a ray hits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Index of the triangle in [`SimpleMesh::triangles`].
+    pub triangle: usize,
+    pub distance: f32,
+    /// Weights of the triangle's vertices at the hit, summing to 1.
+    pub barycentric: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        //! Applies `transform` to the ray. The direction isn't normalized, so distances along the
+        //! transformed ray match those along this one. Pass the inverse of a model matrix to test
+        //! a world space ray against the mesh it places.
+        Self {
+            origin: transform.transform_point3(self.origin),
+            direction: transform.transform_vector3(self.direction),
+        }
+    }
+}
+
+impl RayHit {
+    pub fn point(&self, ray: &Ray) -> Vec3 {
+        //! Where `ray` hits the triangle.
+        ray.at(self.distance)
+    }
+}
+
+impl Triangle {
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        //! Distance along `ray` to where it hits this triangle from either side, along with the
+        //! barycentric weights of the vertices there. Uses the Möller–Trumbore algorithm.
+        let [v0, v1, v2] = self.v.map(Vec3::from);
+        let (edge1, edge2) = (v1 - v0, v2 - v0);
+        let p = ray.direction.cross(edge2);
+        let det = edge1.dot(p);
+        // The ray runs parallel to the triangle. The determinant scales with the lengths of the
+        // edges and the direction, so the tolerance does too.
+        let scale = ray.direction.length() * edge1.length() * edge2.length();
+        if det.abs() <= f32::EPSILON * scale {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = ray.origin - v0;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = ray.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(q) * inv_det;
+        (distance >= 0.0).then(|| (distance, vec3(1.0 - u - v, u, v)))
+    }
+}
+
+impl SimpleMesh {
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<RayHit> {
+        //! The closest triangle `ray` hits, in front of its origin.
+        self.triangles
+            .iter()
+            .enumerate()
+            .filter_map(|(triangle, t)| {
+                t.ray_intersection(ray)
+                    .map(|(distance, barycentric)| RayHit {
+                        triangle,
+                        distance,
+                        barycentric,
+                    })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
@@ -0,0 +1,353 @@
+//! Mesh simplification by quadric error metric edge collapses (Garland & Heckbert), and level of
+//! detail chains built from it.
+pub(crate) use super::*;
+use processing::BoundingSphere;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Successively simplified versions of a mesh, from the original at index 0 to the coarsest.
+#[derive(Debug, Clone)]
+pub struct LodMesh {
+    pub levels: Vec<SimpleMesh>,
+    /// Bounds of the original mesh, used to estimate how large the mesh is on screen.
+    pub bounds: BoundingSphere,
+}
+
+/// Symmetric 4x4 matrix `Q` such that `vᵀQv` is the sum of squared distances from `v` to a set of
+/// planes, stored as its upper triangle.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+/// A candidate edge collapse, ordered by cost so the heap pops the cheapest first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Collapse {
+    cost: f64,
+    edge: (usize, usize),
+    target: Vec3A,
+    /// `versions` of both vertices when this was computed, to spot stale entries.
+    versions: (u32, u32),
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.cost.total_cmp(&other.cost)
+    }
+}
+
+/// Indexed copy of a mesh that edges are collapsed on.
+struct Decimator<'a> {
+    source: &'a [Triangle],
+    positions: Vec<Vec3A>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    /// Faces as vertex indices, `None` once collapsed away.
+    faces: Vec<Option<[usize; 3]>>,
+    vertex_faces: Vec<Vec<usize>>,
+    heap: BinaryHeap<Reverse<Collapse>>,
+}
+
+impl SimpleMesh {
+    pub fn simplified(&self, target_triangles: usize) -> SimpleMesh {
+        //! Collapses edges, cheapest first, until at most `target_triangles` remain or no edge
+        //! can be collapsed without flipping a face. Vertices at the same position are treated as
+        //! shared, and open boundaries are kept in place. Surviving triangles keep their color and
+        //! per vertex attributes.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! let sphere = SimpleMesh::uv_sphere_builder().segments(32).rings(16).build();
+        //! let simple = sphere.simplified(100);
+        //! assert!(simple.triangles.len() <= 100);
+        //! let radius = simple.bounding_sphere().unwrap().radius;
+        //! assert!((radius - 1.0).abs() < 0.1);
+        //! ```
+        if self.triangles.len() <= target_triangles {
+            return self.clone();
+        }
+        let mut decimator = Decimator::new(&self.triangles);
+        decimator.run(target_triangles);
+        decimator.finish()
+    }
+}
+
+impl LodMesh {
+    pub fn new(mesh: SimpleMesh, max_levels: usize) -> Self {
+        //! Builds up to `max_levels` levels, each with half the triangles of the one before. The
+        //! chain stops early once a level gets too small or simplification stalls.
+        let bounds = mesh.bounding_sphere().unwrap_or(BoundingSphere {
+            center: Vec3A::ZERO,
+            radius: 0.0,
+        });
+        let mut levels = vec![mesh];
+        while levels.len() < max_levels {
+            let last = levels.last().unwrap();
+            let target = last.triangles.len() / 2;
+            if target < 8 {
+                break;
+            }
+            let next = last.simplified(target);
+            if next.triangles.len() >= last.triangles.len() {
+                break;
+            }
+            levels.push(next);
+        }
+        Self { levels, bounds }
+    }
+    pub fn level_for_cells(&self, covered_cells: f32, triangles_per_cell: f32) -> usize {
+        //! The most detailed level with at most `triangles_per_cell` front facing triangles for
+        //! each of the `covered_cells` the mesh covers on screen. About half of a closed mesh's
+        //! triangles face away from the camera.
+        let budget = covered_cells.max(1.0) * triangles_per_cell * 2.0;
+        self.levels
+            .iter()
+            .position(|l| l.triangles.len() as f32 <= budget)
+            .unwrap_or(self.levels.len() - 1)
+    }
+}
+
+impl Quadric {
+    fn from_plane(n: Vec3A, d: f32) -> Self {
+        let [a, b, c] = n.to_array().map(|x| x as f64);
+        let d = d as f64;
+        Self([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+    fn scaled(self, s: f64) -> Self {
+        Self(self.0.map(|x| x * s))
+    }
+    fn error(&self, v: Vec3A) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = v.to_array().map(|x| x as f64);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+    fn minimizer(&self) -> Option<Vec3A> {
+        //! The position with the least error, if the quadric isn't degenerate.
+        let q = &self.0;
+        let m =
+            glam::DMat3::from_cols_array(&[q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]]);
+        if m.determinant().abs() < 1e-10 {
+            return None;
+        }
+        let v = m.inverse() * -glam::dvec3(q[3], q[6], q[8]);
+        v.is_finite().then(|| v.as_vec3().into())
+    }
+}
+
+impl std::ops::Add for Quadric {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self {
+        self.0.iter_mut().zip(rhs.0).for_each(|(a, b)| *a += b);
+        self
+    }
+}
+
+impl<'a> Decimator<'a> {
+    fn new(source: &'a [Triangle]) -> Self {
+        let mut index: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let faces = source
+            .iter()
+            .map(|t| {
+                Some(t.v.map(|v| {
+                    *index
+                        .entry(v.to_array().map(f32::to_bits))
+                        .or_insert_with(|| {
+                            positions.push(v);
+                            positions.len() - 1
+                        })
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        let mut decimator = Self {
+            source,
+            quadrics: vec![Quadric::default(); positions.len()],
+            versions: vec![0; positions.len()],
+            vertex_faces: vec![Vec::new(); positions.len()],
+            positions,
+            faces,
+            heap: BinaryHeap::new(),
+        };
+
+        let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+        for (i, face) in decimator.faces.iter().enumerate() {
+            let face = face.unwrap();
+            let p = face.map(|v| decimator.positions[v]);
+            let cross = (p[2] - p[0]).cross(p[1] - p[0]);
+            let area = cross.length() / 2.0;
+            if let Some(n) = cross.try_normalize() {
+                let q = Quadric::from_plane(n, -n.dot(p[0])).scaled(area as f64);
+                face.iter()
+                    .for_each(|v| decimator.quadrics[*v] = decimator.quadrics[*v] + q);
+            }
+            for (j, v) in face.iter().enumerate() {
+                decimator.vertex_faces[*v].push(i);
+                let edge = ordered(*v, face[(j + 1) % 3]);
+                *edge_faces.entry(edge).or_default() += 1;
+            }
+        }
+
+        // Edges on an open boundary get a heavily weighted plane perpendicular to their face, so
+        // collapses don't eat into the outline of the mesh.
+        for face in decimator.faces.iter() {
+            let face = face.unwrap();
+            let p = face.map(|v| decimator.positions[v]);
+            let Some(n) = (p[2] - p[0]).cross(p[1] - p[0]).try_normalize() else {
+                continue;
+            };
+            for j in 0..3 {
+                let (a, b) = (face[j], face[(j + 1) % 3]);
+                if edge_faces[&ordered(a, b)] != 1 {
+                    continue;
+                }
+                let edge = p[(j + 1) % 3] - p[j];
+                if let Some(side) = edge.cross(n).try_normalize() {
+                    let q = Quadric::from_plane(side, -side.dot(p[j]))
+                        .scaled(edge.length_squared() as f64 * 1000.0);
+                    decimator.quadrics[a] = decimator.quadrics[a] + q;
+                    decimator.quadrics[b] = decimator.quadrics[b] + q;
+                }
+            }
+        }
+
+        for edge in edge_faces.keys() {
+            decimator.push_collapse(*edge);
+        }
+        decimator
+    }
+    fn push_collapse(&mut self, (a, b): (usize, usize)) {
+        let q = self.quadrics[a] + self.quadrics[b];
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+        let target = [q.minimizer(), Some(pa), Some(pb), Some((pa + pb) / 2.0)]
+            .into_iter()
+            .flatten()
+            .min_by(|x, y| q.error(*x).total_cmp(&q.error(*y)))
+            .unwrap();
+        self.heap.push(Reverse(Collapse {
+            cost: q.error(target).max(0.0),
+            edge: (a, b),
+            target,
+            versions: (self.versions[a], self.versions[b]),
+        }));
+    }
+    fn live_faces(&self) -> usize {
+        self.faces.iter().flatten().count()
+    }
+    fn run(&mut self, target_triangles: usize) {
+        let mut live = self.live_faces();
+        while live > target_triangles {
+            let Some(Reverse(collapse)) = self.heap.pop() else {
+                break;
+            };
+            let (a, b) = collapse.edge;
+            if collapse.versions != (self.versions[a], self.versions[b]) {
+                continue;
+            }
+            if self.flips_faces(a, b, collapse.target) {
+                continue;
+            }
+            live -= self.collapse(a, b, collapse.target);
+        }
+    }
+    fn flips_faces(&self, a: usize, b: usize, target: Vec3A) -> bool {
+        //! Whether moving `a` and `b` to `target` turns any face that survives the collapse
+        //! around, which would fold the surface over itself.
+        self.vertex_faces[a]
+            .iter()
+            .chain(self.vertex_faces[b].iter())
+            .filter_map(|f| self.faces[*f])
+            .filter(|face| !(face.contains(&a) && face.contains(&b)))
+            .any(|face| {
+                let p = face.map(|v| self.positions[v]);
+                let moved = face.map(|v| {
+                    if v == a || v == b {
+                        target
+                    } else {
+                        self.positions[v]
+                    }
+                });
+                let before = (p[2] - p[0]).cross(p[1] - p[0]);
+                let after = (moved[2] - moved[0]).cross(moved[1] - moved[0]);
+                before.dot(after) <= 0.0
+            })
+    }
+    fn collapse(&mut self, a: usize, b: usize, target: Vec3A) -> usize {
+        //! Merges `b` into `a` at `target`, returning how many faces were removed.
+        self.positions[a] = target;
+        self.quadrics[a] = self.quadrics[a] + self.quadrics[b];
+        self.versions[a] += 1;
+        self.versions[b] += 1;
+
+        let mut removed = 0;
+        let b_faces = std::mem::take(&mut self.vertex_faces[b]);
+        for f in b_faces {
+            let Some(face) = self.faces[f].as_mut() else {
+                continue;
+            };
+            if face.contains(&a) {
+                self.faces[f] = None;
+                removed += 1;
+            } else {
+                face.iter_mut().filter(|v| **v == b).for_each(|v| *v = a);
+                self.vertex_faces[a].push(f);
+            }
+        }
+        let faces = &self.faces;
+        self.vertex_faces[a].retain(|f| faces[*f].is_some());
+
+        let neighbours = self.vertex_faces[a]
+            .iter()
+            .filter_map(|f| self.faces[*f])
+            .flatten()
+            .filter(|v| *v != a)
+            .collect::<HashSet<_>>();
+        for v in neighbours {
+            self.push_collapse(ordered(a, v));
+        }
+        removed
+    }
+    fn finish(self) -> SimpleMesh {
+        self.faces
+            .iter()
+            .zip(self.source.iter())
+            .filter_map(|(face, source)| {
+                let face = (*face)?;
+                let triangle = Triangle {
+                    v: face.map(|v| self.positions[v]),
+                    ..*source
+                };
+                triangle.normal().is_finite().then_some(triangle)
+            })
+            .collect()
+    }
+}
+
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
@@ -0,0 +1,256 @@
+pub(crate) use super::*;
+use std::io::Write;
+use std::path::Path;
+
+/// Size of the binary STL header, followed by a little endian `u32` triangle count.
+const BINARY_HEADER_LEN: usize = 80;
+/// Size of one binary STL facet: normal, three vertices and the attribute byte count.
+const BINARY_FACET_LEN: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StlFormat {
+    Binary,
+    Ascii,
+}
+
+impl SimpleMesh {
+    pub fn load_stl(path: impl AsRef<Path>) -> Result<Self, ResourceError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .change_context_lazy(|| ResourceError::StlLoadError)
+            .attach_printable_lazy(|| format!("could not read {}", path.display()))?;
+        Self::from_stl_bytes(&bytes)
+    }
+    pub fn from_stl_bytes(bytes: &[u8]) -> Result<Self, ResourceError> {
+        //! Parses either STL variant, the format is detected from the contents.
+        //! STL stores counter-clockwise faces, so the second and third vertices are swapped to
+        //! match the winding that [`Triangle::normal`] expects. Every vertex gets the face normal
+        //! of its facet, falling back to the computed normal if the file stores a zero vector.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::resources::stl::StlFormat;
+        //! let mesh: SimpleMesh = [Triangle {
+        //!     v: [vec3a(0.0, 0.0, 0.0), vec3a(0.0, 1.0, 0.0), vec3a(1.0, 0.0, 0.0)],
+        //!     ..Default::default()
+        //! }]
+        //! .into_iter()
+        //! .collect();
+        //! for format in [StlFormat::Binary, StlFormat::Ascii] {
+        //!     let mut bytes = Vec::new();
+        //!     mesh.write_stl(&mut bytes, format).unwrap();
+        //!     let loaded = SimpleMesh::from_stl_bytes(&bytes).unwrap();
+        //!     assert_eq!(loaded.triangles[0].v, mesh.triangles[0].v);
+        //!     assert_eq!(loaded.triangles[0].normal(), vec3a(0.0, 0.0, 1.0));
+        //! }
+        //! // Binary files may start with "solid" and carry bytes after the last facet
+        //! let mut bytes = Vec::new();
+        //! mesh.write_stl(&mut bytes, StlFormat::Binary).unwrap();
+        //! bytes[..5].copy_from_slice(b"solid");
+        //! bytes.extend_from_slice(&[0; 16]);
+        //! let loaded = SimpleMesh::from_stl_bytes(&bytes).unwrap();
+        //! assert_eq!(loaded.triangles.len(), 1);
+        //! assert_eq!(loaded.triangles[0].v, mesh.triangles[0].v);
+        //! ```
+        // Binary files are allowed to start with "solid" too, so a declared triangle count that
+        // fits in the file takes precedence. Some exporters pad or append bytes after the facets,
+        // while the header bytes of an ascii file read as a count of well over a hundred million.
+        let fits_binary = bytes
+            .get(BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as u64)
+            .is_some_and(|count| {
+                (BINARY_HEADER_LEN + 4) as u64 + count * BINARY_FACET_LEN as u64
+                    <= bytes.len() as u64
+            });
+        let starts_with_solid = bytes
+            .iter()
+            .skip_while(|b| b.is_ascii_whitespace())
+            .take(5)
+            .eq(b"solid");
+        if fits_binary || !starts_with_solid {
+            return Self::from_binary_stl(bytes);
+        }
+        let text = std::str::from_utf8(bytes)
+            .change_context_lazy(|| ResourceError::StlLoadError)
+            .attach_printable_lazy(|| "ascii stl is not valid utf8")?;
+        Self::from_ascii_stl(text)
+    }
+    fn from_binary_stl(bytes: &[u8]) -> Result<Self, ResourceError> {
+        let report = |msg: String| Report::new(ResourceError::StlLoadError).attach_printable(msg);
+
+        if bytes.len() < BINARY_HEADER_LEN + 4 {
+            return Err(report(format!(
+                "binary stl is {} bytes, too short for a header",
+                bytes.len()
+            )));
+        }
+        let count = u32::from_le_bytes(
+            bytes[BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        let facets = &bytes[BINARY_HEADER_LEN + 4..];
+        if facets.len() < count * BINARY_FACET_LEN {
+            return Err(report(format!(
+                "binary stl declares {count} triangles but only has room for {}",
+                facets.len() / BINARY_FACET_LEN
+            )));
+        }
+
+        let read_vec = |chunk: &[u8]| {
+            let f = |i: usize| f32::from_le_bytes(chunk[i * 4..i * 4 + 4].try_into().unwrap());
+            vec3a(f(0), f(1), f(2))
+        };
+        Ok(facets
+            .chunks_exact(BINARY_FACET_LEN)
+            .take(count)
+            .map(|facet| {
+                stl_triangle(
+                    read_vec(&facet[0..12]),
+                    [
+                        read_vec(&facet[12..24]),
+                        read_vec(&facet[24..36]),
+                        read_vec(&facet[36..48]),
+                    ],
+                )
+            })
+            .collect())
+    }
+    fn from_ascii_stl(text: &str) -> Result<Self, ResourceError> {
+        let report = |line: usize, msg: &str| {
+            Report::new(ResourceError::StlLoadError)
+                .attach_printable(format!("line {}: {msg}", line + 1))
+        };
+        let parse_vec = |line: usize, words: &[&str]| -> Result<Vec3A, ResourceError> {
+            if words.len() != 3 {
+                return Err(report(line, "expected 3 components"));
+            }
+            let mut v = [0.0; 3];
+            for (c, word) in v.iter_mut().zip(words) {
+                *c = word
+                    .parse::<f32>()
+                    .map_err(|_| report(line, &format!("{word} is not a number")))?;
+            }
+            Ok(Vec3A::from_array(v))
+        };
+
+        let mut triangles = Vec::new();
+        let mut normal = Vec3A::ZERO;
+        let mut polygon: Vec<Vec3A> = Vec::with_capacity(3);
+        for (i, line) in text.lines().enumerate() {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                ["facet", "normal", rest @ ..] => normal = parse_vec(i, rest)?,
+                ["vertex", rest @ ..] => polygon.push(parse_vec(i, rest)?),
+                ["endloop"] => {
+                    if polygon.len() < 3 {
+                        return Err(report(i, "facet has fewer than 3 vertices"));
+                    }
+                    // Some exporters write quads or larger polygons, those are fanned out.
+                    triangles.extend(
+                        (1..polygon.len() - 1).map(|j| {
+                            stl_triangle(normal, [polygon[0], polygon[j], polygon[j + 1]])
+                        }),
+                    );
+                    polygon.clear();
+                }
+                ["solid", ..] | ["endsolid", ..] | ["outer", "loop"] | ["endfacet"] | [] => {}
+                _ => {
+                    return Err(report(
+                        i,
+                        &format!("unexpected statement \"{}\"", line.trim()),
+                    ))
+                }
+            }
+        }
+        Ok(triangles.into_iter().collect())
+    }
+    pub fn save_stl(&self, path: impl AsRef<Path>, format: StlFormat) -> Result<(), ResourceError> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .change_context_lazy(|| ResourceError::StlWriteError)
+            .attach_printable_lazy(|| format!("could not create {}", path.display()))?;
+        self.write_stl(std::io::BufWriter::new(file), format)
+    }
+    pub fn write_stl(
+        &self,
+        mut writer: impl Write,
+        format: StlFormat,
+    ) -> Result<(), ResourceError> {
+        //! Writes the mesh in the requested STL variant. Facet normals are recomputed from the
+        //! vertices, and vertices are written counter-clockwise as the format requires.
+        match format {
+            StlFormat::Binary => self.write_binary_stl(&mut writer),
+            StlFormat::Ascii => self.write_ascii_stl(&mut writer),
+        }
+        .and_then(|_| writer.flush())
+        .change_context_lazy(|| ResourceError::StlWriteError)
+    }
+    fn write_binary_stl(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let count = u32::try_from(self.triangles.len()).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "binary stl can hold at most u32::MAX triangles",
+            )
+        })?;
+        let mut header = [0u8; BINARY_HEADER_LEN];
+        let title = b"binary stl written by text_3d_graphics";
+        header[..title.len()].copy_from_slice(title);
+        writer.write_all(&header)?;
+        writer.write_all(&count.to_le_bytes())?;
+
+        for triangle in self.triangles.iter() {
+            let mut facet = [0u8; BINARY_FACET_LEN];
+            let v = stl_order(triangle);
+            [nan_to_zero(triangle.normal()), v[0], v[1], v[2]]
+                .iter()
+                .flat_map(|v| v.to_array())
+                .zip(facet.chunks_exact_mut(4))
+                .for_each(|(f, bytes)| bytes.copy_from_slice(&f.to_le_bytes()));
+            writer.write_all(&facet)?;
+        }
+        Ok(())
+    }
+    fn write_ascii_stl(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, "solid text_3d_graphics")?;
+        for triangle in self.triangles.iter() {
+            let n = nan_to_zero(triangle.normal());
+            writeln!(writer, "  facet normal {:e} {:e} {:e}", n.x, n.y, n.z)?;
+            writeln!(writer, "    outer loop")?;
+            for v in stl_order(triangle) {
+                writeln!(writer, "      vertex {:e} {:e} {:e}", v.x, v.y, v.z)?;
+            }
+            writeln!(writer, "    endloop")?;
+            writeln!(writer, "  endfacet")?;
+        }
+        writeln!(writer, "endsolid text_3d_graphics")
+    }
+}
+
+/// Builds a triangle from counter-clockwise STL vertices.
+fn stl_triangle(normal: Vec3A, v: [Vec3A; 3]) -> Triangle {
+    let mut triangle = Triangle {
+        v: [v[0], v[2], v[1]],
+        ..Default::default()
+    };
+    let normal = if normal.length_squared() > f32::EPSILON {
+        normal.normalize()
+    } else {
+        nan_to_zero(triangle.normal())
+    };
+    triangle.normals = Some([normal; 3]);
+    triangle
+}
+
+/// The vertices of a triangle in the counter-clockwise order STL uses.
+fn stl_order(triangle: &Triangle) -> [Vec3A; 3] {
+    [triangle.v[0], triangle.v[2], triangle.v[1]]
+}
+
+/// Degenerate triangles have no normal, STL writes those as a zero vector.
+fn nan_to_zero(v: Vec3A) -> Vec3A {
+    if v.is_finite() {
+        v
+    } else {
+        Vec3A::ZERO
+    }
+}
@@ -0,0 +1,351 @@
+//! Scene graphs. Nodes hold a mesh, light or camera each placed relative to their parent, and
+//! [`Rasterizer::render_scene`] draws the whole hierarchy.
+//! ```
+//! use text_3d_graphics::prelude::*;
+//! use text_3d_graphics::resources::gltf::{GltfMesh, GltfNode, GltfPrimitive, GltfScene};
+//! let mut scene = Scene::new();
+//! let node = |name: &str, x: f32| {
+//!     Node::builder()
+//!         .name(name)
+//!         .transform(Transform::from_translation(vec3(x, 0.0, 0.0)))
+//!         .build()
+//! };
+//! let root = scene.add_node(node("root", 1.0), None).unwrap();
+//! let arm = scene.add_node(node("arm", 2.0), Some(root)).unwrap();
+//! let hand = scene.add_node(node("hand", 4.0), Some(arm)).unwrap();
+//! let other = scene.add_node(node("other", 8.0), None).unwrap();
+//!
+//! // Parents come before their children, and each transform builds on its parent's
+//! let transforms = scene.world_transforms();
+//! let order = transforms.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+//! assert_eq!(order, [root, arm, hand, other]);
+//! let x = |m: Mat4| m.w_axis.x;
+//! assert_eq!(transforms.iter().map(|(_, m)| x(*m)).collect::<Vec<_>>(), [1.0, 3.0, 7.0, 8.0]);
+//! assert_eq!(x(scene.world_transform(hand).unwrap()), 7.0);
+//!
+//! // A node can't be moved under its own descendant
+//! assert!(scene.set_parent(root, Some(hand)).is_err());
+//! assert_eq!(scene.node(root).unwrap().parent(), None);
+//! scene.set_parent(hand, Some(other)).unwrap();
+//! assert_eq!(x(scene.world_transform(hand).unwrap()), 12.0);
+//!
+//! // glTF scenes keep their hierarchy, with each mesh's primitives merged
+//! let cube = SimpleMesh::cube_builder().build();
+//! let gltf_node = |name: &str, children: Vec<usize>, mesh| GltfNode {
+//!     name: Some(name.to_owned()),
+//!     translation: vec3(0.0, 1.0, 0.0),
+//!     rotation: Quat::IDENTITY,
+//!     scale: Vec3::ONE,
+//!     children,
+//!     mesh,
+//! };
+//! let primitive = GltfPrimitive { mesh: cube.clone(), material: None };
+//! let gltf = GltfScene {
+//!     nodes: vec![gltf_node("body", vec![1], None), gltf_node("head", vec![], Some(0))],
+//!     roots: vec![0],
+//!     meshes: vec![GltfMesh { name: None, primitives: vec![primitive.clone(), primitive] }],
+//!     materials: vec![],
+//!     textures: vec![],
+//! };
+//! let scene = Scene::from(&gltf);
+//! let head = scene.find("head").unwrap();
+//! assert_eq!(scene.node(head).unwrap().parent(), scene.find("body"));
+//! let mesh = scene.node(head).unwrap().mesh.as_ref().unwrap();
+//! assert_eq!(mesh.triangles.len(), 2 * cube.triangles.len());
+//! assert_eq!(scene.world_transform(head).unwrap().w_axis.y, 2.0);
+//! ```
+pub(crate) use super::*;
+use crate::prelude::*;
+use crate::resources::gltf::GltfScene;
+
+#[derive(Debug, Error)]
+pub enum SceneError {
+    #[error("node {0:?} does not exist in this scene")]
+    MissingNode(NodeId),
+    #[error("making {parent:?} the parent of {child:?} would create a cycle")]
+    Cycle { parent: NodeId, child: NodeId },
+}
+
+/// Translation, rotation and scale of a node relative to its parent. Scale is applied first,
+/// then rotation, then translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub mesh: Option<SimpleMesh>,
+    /// A light placed at the node. Its position and direction are relative to the node.
+    pub light: Option<Light>,
+    /// A camera placed at the node. Its position and directions are relative to the node.
+    pub camera: Option<Camera>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// A hierarchy of nodes, each positioned relative to its parent.
+#[derive(Debug, Clone, Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+    /// The node whose camera [`Rasterizer::render_scene`] renders from, the rasterizer's own
+    /// camera is used when `None`.
+    pub active_camera: Option<NodeId>,
+}
+
+impl NodeId {
+    /// Position of the node in the order it was added to its scene.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl std::default::Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Mat4> for Transform {
+    fn from(value: Mat4) -> Self {
+        let (scale, rotation, translation) = value.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+}
+
+#[buildstructor]
+impl Node {
+    #[builder(visibility = "pub")]
+    pub fn new(
+        name: Option<String>,
+        transform: Option<Transform>,
+        mesh: Option<SimpleMesh>,
+        light: Option<Light>,
+        camera: Option<Camera>,
+    ) -> Self {
+        Self {
+            name,
+            transform: transform.unwrap_or_default(),
+            mesh,
+            light,
+            camera,
+            parent: None,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Node {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add_node(
+        &mut self,
+        mut node: Node,
+        parent: Option<NodeId>,
+    ) -> Result<NodeId, SceneError> {
+        if let Some(parent) = parent {
+            self.get(parent)?;
+        }
+        let id = NodeId(self.nodes.len());
+        node.parent = None;
+        node.children.clear();
+        self.nodes.push(node);
+        self.roots.push(id);
+        self.set_parent(id, parent)?;
+        Ok(id)
+    }
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)
+    }
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0)
+    }
+    fn get(&self, id: NodeId) -> Result<&Node, SceneError> {
+        self.node(id)
+            .ok_or_else(|| Report::new(SceneError::MissingNode(id)))
+    }
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().enumerate().map(|(i, n)| (NodeId(i), n))
+    }
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes()
+            .find(|(_, n)| n.name.as_deref() == Some(name))
+            .map(|(id, _)| id)
+    }
+    pub fn set_parent(&mut self, child: NodeId, parent: Option<NodeId>) -> Result<(), SceneError> {
+        //! Moves `child` and its descendants under `parent`, or to the top of the hierarchy when
+        //! `parent` is `None`. The child's local transform is kept, so it moves with its new
+        //! parent.
+        self.get(child)?;
+        if let Some(parent) = parent {
+            self.get(parent)?;
+            let mut ancestor = Some(parent);
+            while let Some(a) = ancestor {
+                if a == child {
+                    return Err(Report::new(SceneError::Cycle { parent, child }));
+                }
+                ancestor = self.nodes[a.0].parent;
+            }
+        }
+
+        match self.nodes[child.0].parent {
+            Some(old) => self.nodes[old.0].children.retain(|c| *c != child),
+            None => self.roots.retain(|c| *c != child),
+        }
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(child),
+            None => self.roots.push(child),
+        }
+        self.nodes[child.0].parent = parent;
+        Ok(())
+    }
+    pub fn world_transform(&self, id: NodeId) -> Result<Mat4, SceneError> {
+        let mut transform = self.get(id)?.transform.matrix();
+        let mut ancestor = self.nodes[id.0].parent;
+        while let Some(a) = ancestor {
+            transform = self.nodes[a.0].transform.matrix() * transform;
+            ancestor = self.nodes[a.0].parent;
+        }
+        Ok(transform)
+    }
+    pub fn world_transforms(&self) -> Vec<(NodeId, Mat4)> {
+        //! Every node with its transform into world space, parents before their children.
+        let mut out = Vec::with_capacity(self.nodes.len());
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|id| (*id, Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+        while let Some((id, parent)) = stack.pop() {
+            let node = &self.nodes[id.0];
+            let world = parent * node.transform.matrix();
+            out.push((id, world));
+            stack.extend(node.children.iter().rev().map(|c| (*c, world)));
+        }
+        out
+    }
+}
+
+impl From<&GltfScene> for Scene {
+    fn from(value: &GltfScene) -> Self {
+        //! Recreates the default scene's hierarchy. The primitives of each glTF mesh are merged
+        //! into a single mesh. Each glTF node is added once, so a node listed under several
+        //! parents of a hand-built [`GltfScene`] only appears under the first one reached
+        //! walking depth first, and cycles are cut where they close.
+        //! ```
+        //! use text_3d_graphics::prelude::*;
+        //! use text_3d_graphics::resources::gltf::{GltfNode, GltfScene};
+        //! let node = |name: &str, children: Vec<usize>| GltfNode {
+        //!     name: Some(name.to_owned()),
+        //!     translation: Vec3::ZERO,
+        //!     rotation: Quat::IDENTITY,
+        //!     scale: Vec3::ONE,
+        //!     children,
+        //!     mesh: None,
+        //! };
+        //! // "shared" is listed under both "a" and "b", and "b" and "c" list each other
+        //! let gltf = GltfScene {
+        //!     nodes: vec![
+        //!         node("a", vec![1, 3]),
+        //!         node("b", vec![2, 3]),
+        //!         node("c", vec![1]),
+        //!         node("shared", vec![]),
+        //!     ],
+        //!     roots: vec![0],
+        //!     meshes: vec![],
+        //!     materials: vec![],
+        //!     textures: vec![],
+        //! };
+        //! let scene = Scene::from(&gltf);
+        //! assert_eq!(scene.nodes().count(), 4);
+        //! let parent = |name| scene.node(scene.find(name).unwrap()).unwrap().parent();
+        //! // "b" is walked before the second child of "a"
+        //! assert_eq!(parent("shared"), scene.find("b"));
+        //! assert_eq!(parent("c"), scene.find("b"));
+        //! assert_eq!(parent("b"), scene.find("a"));
+        //! ```
+        let meshes = value
+            .meshes
+            .iter()
+            .map(|m| {
+                m.primitives
+                    .iter()
+                    .flat_map(|p| p.mesh.triangles.iter().copied())
+                    .collect::<SimpleMesh>()
+            })
+            .collect::<Vec<_>>();
+        let mut scene = Scene::new();
+        let mut visited = vec![false; value.nodes.len()];
+        let mut stack = value
+            .roots
+            .iter()
+            .rev()
+            .map(|i| (*i, None))
+            .collect::<Vec<_>>();
+        while let Some((i, parent)) = stack.pop() {
+            let Some(source) = value.nodes.get(i) else {
+                continue;
+            };
+            if std::mem::replace(&mut visited[i], true) {
+                continue;
+            }
+            let node = Node {
+                name: source.name.clone(),
+                transform: Transform {
+                    translation: source.translation,
+                    rotation: source.rotation,
+                    scale: source.scale,
+                },
+                mesh: source.mesh.and_then(|m| meshes.get(m).cloned()),
+                ..Node::builder().build()
+            };
+            // Only nodes that were added push their children, so the parent always exists
+            let id = scene.add_node(node, parent).unwrap();
+            stack.extend(source.children.iter().rev().map(|c| (*c, Some(id))));
+        }
+        scene
+    }
+}